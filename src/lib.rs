@@ -76,34 +76,59 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! # Cargo features
+//!
+//! - `metrics`: record per-actor mailbox length, message counts, handler
+//!   durations, panics and restarts, readable through `Addr::stats()` and
+//!   forwarded to an installable `MetricsExporter`.
+//! - `tracing`: report failures through [`tracing`](https://docs.rs/tracing)
+//!   and run every handler inside a `handle` span carrying the actor type,
+//!   actor id and message type. The span follows from the sender's span
+//!   current at `tell`/`ask` time, so causality is visible across actors.
+//! - `serde`: implement `serde::Serialize` for the `ActorSnapshot` returned by
+//!   `ActorSystem::snapshot_tree()`.
 
 use async_trait::async_trait;
-use futures::FutureExt;
+use futures::future::{self, BoxFuture};
+use futures::stream::{self, Stream};
+use futures::{FutureExt, StreamExt};
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::future::Future;
-use std::sync::OnceLock;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::io;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{OnceLock, RwLock, Weak};
 use std::{
     panic::AssertUnwindSafe,
     sync::{Arc, Mutex},
-    time::Instant,
+    time::{Duration, Instant},
 };
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio_util::sync::CancellationToken;
 
 pub use tactix_macros::Message;
 
-/// The global root actor system.
+/// The root actor of an actor system.
 ///
-/// `ActorSystem` is a singleton sentinel actor that serves as the root of the
-/// actor hierarchy. Actors spawned via [`Actor::start()`] are registered as
+/// `ActorSystem` is a sentinel actor that serves as the root of the actor
+/// hierarchy. Actors spawned via [`Actor::start()`] are registered as
 /// children of the system; when the system stops, all children are stopped
 /// recursively.
 ///
 /// # Access
 ///
-/// - [`ActorSystem::global()`] returns the system's [`Ctx`], giving you access
-///   to [`spawn`](Ctx::spawn) for manual child creation.
-/// - [`ActorSystem::addr()`] returns the system's [`Addr`], so you can send it
-///   messages — most importantly [`Shutdown`].
+/// - [`ActorSystem::global()`] returns the global system's [`Ctx`], giving you
+///   access to [`spawn`](Ctx::spawn) for manual child creation.
+/// - [`ActorSystem::addr()`] returns the global system's [`Addr`], so you can
+///   send it messages — most importantly [`Shutdown`].
+/// - [`ActorSystem::new()`] starts an independent system and returns its
+///   root [`Ctx`]. The associated functions on `ActorSystem` all act on the
+///   global system; the same operations are available as methods on a
+///   `Ctx<ActorSystem>`.
 ///
 /// # Graceful shutdown
 ///
@@ -120,14 +145,46 @@ pub use tactix_macros::Message;
 /// messages sent *before* it are processed first. This ensures an orderly
 /// wind-down.
 ///
+/// Tasks registered with [`ActorSystem::add_shutdown_task()`] run before or
+/// after the actors stop, depending on their [`ShutdownPhase`]. Actors that
+/// exceed their [`stop_timeout`](ActorConfig::stop_timeout) are aborted and
+/// listed in the returned [`ShutdownReport`].
+///
+/// Services typically end `main` with
+/// [`ActorSystem::run_until_signal().await`](ActorSystem::run_until_signal),
+/// which starts this shutdown on SIGINT or SIGTERM and forces it on a second
+/// signal.
+///
+/// # Dead letters
+///
+/// Messages that are lost — sent to a stopped actor, left in a mailbox when
+/// an actor stops, or asks whose reply was dropped — are published as
+/// [`DeadLetter`]s. Subscribe with [`ActorSystem::dead_letters()`].
+///
+/// # Failure reporting
+///
+/// Panics, restarts and escalations are reported as [`FailureEvent`]s to the
+/// system's [`FailureReporter`], installed with
+/// [`ActorSystem::set_failure_reporter()`]. The default writes to stderr, or
+/// to [`tracing`](https://docs.rs/tracing) when the `tracing` feature is
+/// enabled.
+///
+/// # Metrics
+///
+/// With the `metrics` feature every actor keeps counters readable through
+/// `Addr::stats()`. To collect them centrally install a `MetricsExporter`,
+/// such as the in-process `MetricsRegistry`, with
+/// `ActorSystem::set_metrics_exporter()`.
+///
 /// # Panics
 ///
 /// The global system is initialised lazily on first access and cannot be
-/// re-initialised afterwards. Sending messages to the system after it has
+/// re-initialised afterwards; use [`ActorSystem::new()`] where a fresh system
+/// is needed, e.g. one per test. Sending messages to a system after it has
 /// shut down is a no-op (the message is dropped).
 pub struct ActorSystem;
 
-/// Message to gracefully shut down the global actor system.
+/// Message to gracefully shut down an actor system.
 ///
 /// Sending this to [`ActorSystem::addr()`] triggers a graceful shutdown:
 /// the system finishes processing the current message, stops all children,
@@ -138,7 +195,13 @@ pub struct ActorSystem;
 #[derive(Message)]
 pub struct Shutdown;
 
-impl Actor for ActorSystem {}
+impl Actor for ActorSystem {
+    /// A top-level actor that exhausts its restart budget is stopped; the
+    /// system and the other top-level actors keep running.
+    async fn child_escalated(&mut self, _: &ChildFailure, _: &Ctx<Self>) -> Option<Interrupt> {
+        None
+    }
+}
 
 impl Handler<Shutdown> for ActorSystem {
     async fn handle(&mut self, _: Shutdown, ctx: &Ctx<Self>) {
@@ -146,893 +209,6071 @@ impl Handler<Shutdown> for ActorSystem {
     }
 }
 
-static ACTOR_SYSTEM: OnceLock<Ctx<ActorSystem>> = OnceLock::new();
+/// A stage of [`Ctx::shutdown`] that shutdown tasks can be registered into
+/// with [`Ctx::add_shutdown_task`].
+///
+/// Phases run in declaration order, and the tasks within one phase run
+/// concurrently. Actors are stopped between the two phases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShutdownPhase {
+    /// Runs while every actor is still running, e.g. to stop accepting new
+    /// work or to drain requests in flight.
+    BeforeActorsStop,
+    /// Runs once every actor has stopped or been aborted, e.g. to flush and
+    /// close external resources.
+    AfterActorsStop,
+}
 
-impl ActorSystem {
-    /// Returns a reference to the global `ActorSystem` context, initialising it
-    /// on first call.
-    pub fn global() -> &'static Ctx<ActorSystem> {
-        ACTOR_SYSTEM.get_or_init(|| {
-            let mut once = Some(ActorSystem);
-            start_actor(
-                move || {
-                    once.take()
-                        .expect("ActorSystem factory called more than once")
-                },
-                CancellationToken::new(),
-                mpsc::unbounded_channel().0,
-                SupervisionStrategy::default(),
-            )
-        })
+/// An actor that did not stop within its stop timeout and was aborted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbortedActor {
+    /// Identity of the actor.
+    pub id: ActorId,
+    /// Type name of the actor.
+    pub actor_type: &'static str,
+    /// The timeout it exceeded.
+    pub timeout: Duration,
+}
+
+/// What went wrong during [`Ctx::shutdown`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Actors that did not stop in time, each listed before its children.
+    pub aborted: Vec<AbortedActor>,
+    /// Name and phase of every shutdown task that had not finished when its
+    /// phase timed out.
+    pub timed_out_tasks: Vec<(ShutdownPhase, String)>,
+}
+
+impl ShutdownReport {
+    /// Returns `true` if every actor stopped and every shutdown task finished
+    /// in time.
+    pub fn is_clean(&self) -> bool {
+        self.aborted.is_empty() && self.timed_out_tasks.is_empty()
     }
+}
 
-    /// Returns the address of the global `ActorSystem`.
-    ///
-    /// Use this to send messages (e.g. [`Shutdown`]) to the system from
-    /// anywhere in your application.
-    pub fn addr() -> Addr<ActorSystem> {
-        Self::global().address()
+type ShutdownTask = (ShutdownPhase, String, BoxFuture<'static, ()>);
+
+/// The signals that trigger [`Ctx::run_until_signal`].
+struct Signals {
+    #[cfg(unix)]
+    interrupt: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    fn new() -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            Ok(Self {
+                interrupt: signal(SignalKind::interrupt())?,
+                terminate: signal(SignalKind::terminate())?,
+            })
+        }
+        #[cfg(not(unix))]
+        Ok(Self {})
     }
 
-    /// Gracefully shut down the entire actor system.
-    ///
-    /// Sends [`Shutdown`] to the system and waits for it to finish processing
-    /// pending messages, stop all children, and exit.
-    pub async fn shutdown() {
-        let addr = Self::addr();
-        addr.tell(Shutdown);
-        addr.wait_until_stopped().await;
+    /// Wait for the next signal.
+    async fn recv(&mut self) {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = self.interrupt.recv() => {}
+            _ = self.terminate.recv() => {}
+        }
+        #[cfg(not(unix))]
+        let _ = tokio::signal::ctrl_c().await;
+    }
+
+    /// Yield an item for every signal received.
+    fn into_stream(self) -> impl Stream<Item = ()> {
+        stream::unfold(self, |mut signals| async move {
+            signals.recv().await;
+            Some(((), signals))
+        })
     }
 }
 
-type PointerToActorMessage<A> = Box<dyn ActorMessage<A>>;
+static ACTOR_SYSTEM: OnceLock<Ctx<ActorSystem>> = OnceLock::new();
 
-/// Trait implemented by all actors.
+/// Path of every system's root actor.
+const ROOT_PATH: &str = "/system";
+
+/// Number of dead letters buffered per subscriber before the oldest are
+/// dropped.
+const DEAD_LETTER_CAPACITY: usize = 1024;
+
+/// Configuration for an actor system created with [`ActorSystem::new`].
 ///
-/// Actors are long-lived, stateful objects that communicate via message passing.
-/// Each actor runs in its own Tokio task and processes messages sequentially.
+/// Usually assembled with an [`ActorSystemBuilder`].
+#[derive(Clone, Debug)]
+pub struct ActorSystemConfig {
+    /// Supervision for children spawned without an explicit strategy.
+    /// Defaults to [`SupervisionStrategy::default`].
+    pub supervision: SupervisionStrategy,
+    /// Mailbox for actors spawned without an explicit one. Defaults to
+    /// [`Mailbox::Unbounded`].
+    pub mailbox: Mailbox,
+    /// Runtime the system's actor tasks are spawned on. Defaults to `None`,
+    /// meaning whichever runtime is current when an actor is spawned.
+    pub runtime: Option<tokio::runtime::Handle>,
+    /// How long each phase of [`Ctx::shutdown`] may take. Shutdown tasks
+    /// still running afterwards are abandoned and actors still running are
+    /// aborted. Defaults to `None`, waiting indefinitely.
+    pub shutdown_timeout: Option<Duration>,
+    /// How long a parent waits for a child spawned without an explicit
+    /// [`stop_timeout`](ActorConfig::stop_timeout) to stop. Defaults to
+    /// `None`, waiting indefinitely.
+    pub stop_timeout: Option<Duration>,
+    /// Dead letters buffered per [subscriber](Ctx::dead_letters) before the
+    /// oldest are dropped. Defaults to 1024.
+    pub dead_letter_capacity: usize,
+}
+
+impl Default for ActorSystemConfig {
+    fn default() -> Self {
+        Self {
+            supervision: SupervisionStrategy::default(),
+            mailbox: Mailbox::default(),
+            runtime: None,
+            shutdown_timeout: None,
+            stop_timeout: None,
+            dead_letter_capacity: DEAD_LETTER_CAPACITY,
+        }
+    }
+}
+
+/// Builder for an actor system with its own defaults.
 ///
-/// # Lifecycle
+/// ```rust
+/// use std::num::NonZeroUsize;
+/// use std::time::Duration;
+/// use tactix::{ActorSystem, Mailbox, SupervisionStrategy};
 ///
-/// 1. [`started`](Actor::started) is called once before the first message.
-/// 2. Messages are handled one-by-one via [`Handler`] implementations.
-/// 3. If a handler panics, the actor restarts according to [`SupervisionStrategy`];
-///    [`restarted`](Actor::restarted) is called after each restart.
-/// 4. When stopped (via [`Ctx::stop`] or `SupervisionStrategy::NoRestart`),
-///    [`stopped`](Actor::stopped) is called and the task exits.
-pub trait Actor: Send + Sized + 'static {
-    /// Spawn this actor on the global system with default supervision
-    /// ([`SupervisionStrategy::NoRestart`]) and return its address.
-    ///
-    /// To configure supervision or spawn as a child of another actor, use
-    /// [`Ctx::spawn`] or [`Ctx::spawn_with_config`] instead.
-    fn start(self) -> Addr<Self> {
-        let mut once = Some(self);
-        ActorSystem::global().spawn_with_config(
-            move || once.take().expect("Factory can only be accessed once!"),
-            SupervisionStrategy::NoRestart,
-        )
+/// # #[tokio::main]
+/// # async fn main() {
+/// let system = ActorSystem::builder()
+///     .mailbox(Mailbox::Bounded(NonZeroUsize::new(1024).unwrap()))
+///     .supervision(SupervisionStrategy::NoRestart)
+///     .shutdown_timeout(Duration::from_secs(5))
+///     .build();
+/// # system.shutdown().await;
+/// # }
+/// ```
+#[derive(Default)]
+pub struct ActorSystemBuilder {
+    config: ActorSystemConfig,
+    failure_reporter: Option<Arc<dyn FailureReporter>>,
+    #[cfg(feature = "metrics")]
+    metrics_exporter: Option<Arc<dyn MetricsExporter>>,
+}
+
+impl ActorSystemBuilder {
+    /// Start from the default [`ActorSystemConfig`].
+    pub fn new() -> Self {
+        Self::default()
     }
-    /// Called after the actor task starts, before any messages are processed.
-    fn started(&mut self, _ctx: &Ctx<Self>) -> impl Future<Output = ()> + Send {
-        async {}
+
+    /// Start from an existing configuration.
+    pub fn from_config(config: ActorSystemConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
     }
-    /// Called after the actor has stopped (all messages drained, children
-    /// stopped) and the task is about to exit.
-    fn stopped(&mut self, _ctx: &Ctx<Self>) -> impl Future<Output = ()> + Send {
-        async {}
+
+    /// See [`ActorSystemConfig::supervision`].
+    pub fn supervision(mut self, supervision: SupervisionStrategy) -> Self {
+        self.config.supervision = supervision;
+        self
     }
-    /// Called after a restart before the first message is processed.
-    ///
-    /// `restarts` is the total number of restarts that have occurred.
-    fn restarted(&mut self, _restarts: u64, _ctx: &Ctx<Self>) -> impl Future<Output = ()> + Send {
-        async {}
+
+    /// See [`ActorSystemConfig::mailbox`].
+    pub fn mailbox(mut self, mailbox: Mailbox) -> Self {
+        self.config.mailbox = mailbox;
+        self
     }
-    /// Called when a child actor has escalated after exhausting its restart
-    /// budget.
+
+    /// See [`ActorSystemConfig::runtime`].
+    pub fn runtime(mut self, runtime: tokio::runtime::Handle) -> Self {
+        self.config.runtime = Some(runtime);
+        self
+    }
+
+    /// See [`ActorSystemConfig::shutdown_timeout`].
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.config.shutdown_timeout = Some(timeout);
+        self
+    }
+
+    /// See [`ActorSystemConfig::stop_timeout`].
+    pub fn stop_timeout(mut self, timeout: Duration) -> Self {
+        self.config.stop_timeout = Some(timeout);
+        self
+    }
+
+    /// See [`ActorSystemConfig::dead_letter_capacity`].
+    pub fn dead_letter_capacity(mut self, capacity: usize) -> Self {
+        self.config.dead_letter_capacity = capacity;
+        self
+    }
+
+    /// Report failures to `reporter` instead of the default, see
+    /// [`Ctx::set_failure_reporter`].
+    pub fn failure_reporter(mut self, reporter: impl FailureReporter) -> Self {
+        self.failure_reporter = Some(Arc::new(reporter));
+        self
+    }
+
+    /// Export metrics to `exporter`, see [`Ctx::set_metrics_exporter`].
+    #[cfg(feature = "metrics")]
+    pub fn metrics_exporter(mut self, exporter: impl MetricsExporter) -> Self {
+        self.metrics_exporter = Some(Arc::new(exporter));
+        self
+    }
+
+    /// Start the system and return its root context.
     ///
-    /// Return `Some(Interrupt)` to influence the parent's behaviour (default:
-    /// [`Interrupt::RestartToEscalate`]), or `None` to ignore the escalation.
-    fn child_escalated(
-        &mut self,
-        _ctx: &Ctx<Self>,
-    ) -> impl Future<Output = Option<Interrupt>> + Send {
-        async { Some(Interrupt::RestartToEscalate) }
+    /// Spawns the root actor, so this must be called from within a Tokio
+    /// runtime unless a [`runtime`](ActorSystemBuilder::runtime) is given.
+    pub fn build(self) -> Ctx<ActorSystem> {
+        let mut state = SystemState::new(self.config);
+        if let Some(reporter) = self.failure_reporter {
+            state.failure_reporter = RwLock::new(reporter);
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(exporter) = self.metrics_exporter {
+            state.metrics_exporter = RwLock::new(Some(exporter));
+        }
+        let mut once = Some(ActorSystem);
+        let (root, addr) = start_actor(
+            move |previous| {
+                previous
+                    .or_else(|| once.take())
+                    .expect("ActorSystem is handed back on restart")
+            },
+            ActorId::next(),
+            ROOT_PATH.into(),
+            CancellationToken::new(),
+            mpsc::unbounded_channel().0,
+            ActorConfig {
+                supervision: Some(SupervisionStrategy::default()),
+                mailbox: Some(Mailbox::Unbounded),
+                ..Default::default()
+            },
+            Arc::new(state),
+        );
+        let system = &root.addr.system;
+        let _ = system.root.set(Arc::downgrade(&root.children));
+        let _ = system.root_tx.set(addr.tx);
+        root
     }
 }
 
-/// Outcome of an actor's event loop iteration that drives lifecycle decisions.
-pub enum Interrupt {
-    /// Gracefully stop the actor.
-    Stop,
-    /// Restart the actor and, if the restart budget is exhausted, escalate
-    /// to the parent.
-    RestartToEscalate,
+/// State shared by every actor in one system.
+struct SystemState {
+    config: ActorSystemConfig,
+    dead_letters: broadcast::Sender<DeadLetter>,
+    failure_reporter: RwLock<Arc<dyn FailureReporter>>,
+    #[cfg(feature = "metrics")]
+    metrics_exporter: RwLock<Option<Arc<dyn MetricsExporter>>>,
+    shutdown_tasks: Mutex<Vec<ShutdownTask>>,
+    /// Actors aborted since shutdown began, or `None` outside of shutdown.
+    aborted: Mutex<Option<Vec<AbortedActor>>>,
+    /// Actors spawned with [`Ctx::spawn_named`], by path.
+    registry: Mutex<HashMap<String, Registered>>,
+    /// Children of the root actor, for resolving [`ActorSelection`]s.
+    root: OnceLock<Weak<Children>>,
+    /// Keeps the root actor's mailbox open: the root is stopped by
+    /// [`Ctx::shutdown`], not by its addresses being dropped.
+    root_tx: OnceLock<MailboxTx<ActorSystem>>,
 }
 
-/// Supervision strategy that controls how panics in an actor are handled.
-pub enum SupervisionStrategy {
-    /// Do not restart on panic. The actor task exits immediately with no
-    /// escalation to the parent.
-    NoRestart,
-    /// Restart up to `max_restarts` times within a sliding `window` of seconds.
-    ///
-    /// Once the budget is exhausted the actor stops and an escalation signal
-    /// is sent to the parent actor's [`child_escalated`](Actor::child_escalated)
-    /// hook.
-    Restart { window: u64, max_restarts: u64 },
+/// A named actor's entry in its system's registry.
+struct Registered {
+    id: ActorId,
+    stopped: CancellationToken,
+    /// The actor's `Addr<A>`, downcast on lookup.
+    addr: Box<dyn Any + Send + Sync>,
 }
 
-impl Default for SupervisionStrategy {
-    fn default() -> Self {
-        Self::Restart {
-            window: 5,
-            max_restarts: 3,
+impl SystemState {
+    fn new(config: ActorSystemConfig) -> Self {
+        #[cfg(feature = "tracing")]
+        let reporter = Arc::new(TracingReporter);
+        #[cfg(not(feature = "tracing"))]
+        let reporter = Arc::new(StderrReporter);
+        Self {
+            dead_letters: broadcast::channel(config.dead_letter_capacity).0,
+            config,
+            failure_reporter: RwLock::new(reporter),
+            #[cfg(feature = "metrics")]
+            metrics_exporter: RwLock::new(None),
+            shutdown_tasks: Mutex::new(Vec::new()),
+            aborted: Mutex::new(None),
+            registry: Mutex::new(HashMap::new()),
+            root: OnceLock::new(),
+            root_tx: OnceLock::new(),
         }
     }
-}
-
-fn start_actor<A, F>(
-    mut factory: F,
-    cancel: CancellationToken,
-    escalate_to_parent: mpsc::UnboundedSender<()>,
-    restart_config: SupervisionStrategy,
-) -> Ctx<A>
-where
-    A: Actor,
-    F: FnMut() -> A + Send + 'static,
-{
-    let (tx, mut rx) = mpsc::unbounded_channel::<PointerToActorMessage<A>>();
-    let (child_escalations, mut child_escalations_rx) = mpsc::unbounded_channel();
-    let stopped = CancellationToken::new();
-    let ctx = Ctx::<A> {
-        addr: Addr {
-            tx,
-            stopped: stopped.clone(),
-        },
-        children: Arc::new(Mutex::new(Vec::new())),
-        cancel,
-        stopped: stopped.clone(),
-        child_escalations,
-    };
-    let ctx_loop = ctx.clone();
-    tokio::spawn(async move {
-        let mut is_restart = false;
-        let ctx = ctx_loop;
-        let _stopped_guard = stopped.drop_guard();
 
-        let mut restarts = 0u64;
-        let mut first_restart = Instant::now();
-        loop {
-            let mut actor = factory();
-            actor.started(&ctx).await;
-            if is_restart {
-                actor.restarted(restarts, &ctx).await;
-            }
-            let code = loop {
-                tokio::select! {
-                    biased; // Important makes sure we check in order
+    fn spawn<F>(&self, future: F) -> tokio::task::JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match &self.config.runtime {
+            Some(runtime) => runtime.spawn(future),
+            None => tokio::spawn(future),
+        }
+    }
 
-                    // Check the cancel signal
-                    _ = ctx.cancel.cancelled() => {
-                        break Interrupt::Stop;
-                    }
+    fn dead_letter(&self, letter: DeadLetter) {
+        // No subscribers is not an error: the letter is simply discarded.
+        let _ = self.dead_letters.send(letter);
+    }
 
-                    // Check if our children have escalated
-                    Some(_) = child_escalations_rx.recv() => {
-                        if let Some(interrupt) = actor.child_escalated(&ctx).await {
-                            break interrupt;
-                        }
-                    }
+    fn report(&self, event: FailureEvent) {
+        let reporter = self.failure_reporter.read().unwrap().clone();
+        reporter.report(&event);
+    }
 
-                    // Receive a message
-                    msg = rx.recv() => {
-                        let Some(mut msg) = msg else {
-                            break Interrupt::Stop;
-                        };
-                        if let Err(panic) = AssertUnwindSafe(msg.process(&mut actor, &ctx))
-                            .catch_unwind()
-                            .await
-                        {
-                            let msg = panic.downcast_ref::<&str>().copied()
-                                .or_else(|| panic.downcast_ref::<String>().map(|s| s.as_str()))
-                                .unwrap_or("<non-string panic>");
+    fn abort(&self, aborted: AbortedActor) {
+        if let Some(report) = self.aborted.lock().unwrap().as_mut() {
+            report.push(aborted.clone());
+        }
+        self.report(FailureEvent::Aborted(aborted));
+    }
 
-                            eprintln!("ACTOR PANIC!\n actor:{}\n reason: {}\n restarting...", std::any::type_name::<A>(), msg);
-                            break Interrupt::RestartToEscalate;
-                        }
-                    }
+    /// Run the shutdown tasks registered for `phase`, returning the names of
+    /// those that did not finish within the shutdown timeout.
+    async fn run_shutdown_phase(&self, phase: ShutdownPhase) -> Vec<(ShutdownPhase, String)> {
+        let tasks: Vec<_> = {
+            let mut tasks = self.shutdown_tasks.lock().unwrap();
+            let (run, keep) = tasks.drain(..).partition(|(p, ..)| *p == phase);
+            *tasks = keep;
+            run
+        };
+        let running: Vec<_> = tasks
+            .into_iter()
+            .map(|(_, name, task)| (name, self.spawn(task)))
+            .collect();
+        let deadline = self
+            .config
+            .shutdown_timeout
+            .map(|timeout| tokio::time::Instant::now() + timeout);
+        let mut timed_out = Vec::new();
+        for (name, mut task) in running {
+            let finished = match deadline {
+                Some(deadline) => tokio::time::timeout_at(deadline, &mut task).await.is_ok(),
+                None => {
+                    let _ = (&mut task).await;
+                    true
                 }
             };
-
-            // Stop and wait for all children regardless of why we exited.
-            ctx.stop_all_children().await;
-
-            match code {
-                Interrupt::Stop => {
-                    actor.stopped(&ctx).await;
-                    break;
-                }
-                Interrupt::RestartToEscalate => {
-                    match &restart_config {
-                        SupervisionStrategy::NoRestart => {
-                            // Unsupervised: just exit, do not escalate.
-                            break;
-                        }
-                        SupervisionStrategy::Restart {
-                            window,
-                            max_restarts,
-                        } => {
-                            is_restart = true;
-                            if first_restart.elapsed().as_secs() > *window {
-                                restarts = 0;
-                                first_restart = Instant::now();
-                            }
-                            restarts += 1;
-                            if restarts >= *max_restarts {
-                                eprintln!(
-                                    "Actor restarted {} times in {}s, escalating.",
-                                    max_restarts, window
-                                );
-                                let _ = escalate_to_parent.send(());
-                                break;
-                            }
-                        }
-                    }
-                }
+            if !finished {
+                task.abort();
+                timed_out.push((phase, name));
             }
         }
-        // _stopped_guard drops here and signals `stopped`.
-    });
-    ctx
+        timed_out
+    }
+
+    #[cfg(feature = "metrics")]
+    fn export(&self, event: MetricsEvent) {
+        let exporter = self.metrics_exporter.read().unwrap().clone();
+        if let Some(exporter) = exporter {
+            exporter.export(&event);
+        }
+    }
 }
 
-/// Context handle for an actor.
-///
-/// Provides access to the actor's address and the ability to spawn child actors.
-/// Every [`Handler`] receives a reference to `Ctx` so actors can supervise their
-/// children.
-///
-/// Cloning `Ctx` is cheap (it uses `Arc` internally).
-pub struct Ctx<A: Actor> {
-    addr: Addr<A>,
-    children: Arc<Mutex<Vec<Arc<dyn Stoppable + Send + Sync>>>>,
-    cancel: CancellationToken,
-    stopped: CancellationToken,
-    child_escalations: mpsc::UnboundedSender<()>,
+/// Description of a message that was never handled or never answered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetter {
+    /// Type name of the actor the message was addressed to.
+    pub actor: &'static str,
+    /// Type name of the message.
+    pub message: &'static str,
+    /// Why the message was lost.
+    pub reason: DeadLetterReason,
 }
 
-impl<A: Actor> Clone for Ctx<A> {
-    fn clone(&self) -> Self {
+/// Why a [`DeadLetter`] was not delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadLetterReason {
+    /// The actor had stopped and no longer accepted messages.
+    Stopped,
+    /// The actor's [bounded](Mailbox::Bounded) mailbox was full.
+    MailboxFull,
+    /// The message was still queued in the mailbox when the actor stopped.
+    Unprocessed,
+    /// The sender's deadline passed before the actor reached the message.
+    Expired,
+    /// The message was taken from the mailbox but its reply was dropped.
+    NoReply,
+}
+
+impl DeadLetter {
+    fn new<A: Actor, M: Message>(reason: DeadLetterReason) -> Self {
         Self {
-            addr: self.addr.clone(),
-            children: self.children.clone(),
-            cancel: self.cancel.clone(),
-            stopped: self.stopped.clone(),
-            child_escalations: self.child_escalations.clone(),
+            actor: std::any::type_name::<A>(),
+            message: std::any::type_name::<M>(),
+            reason,
         }
     }
 }
 
-impl<A: Actor> Ctx<A> {
-    /// Returns the address of this actor.
-    #[must_use]
-    pub fn address(&self) -> Addr<A> {
-        self.addr.clone()
-    }
+/// A supervision event worth surfacing to logging or alerting.
+#[derive(Clone, Debug)]
+pub enum FailureEvent {
+    /// A handler panicked while processing a message.
+    Panicked {
+        /// The actor whose handler panicked.
+        id: ActorId,
+        /// Type name of the actor.
+        actor_type: &'static str,
+        /// The panic message and faulting message type.
+        panic: PanicInfo,
+    },
+    /// A panicked actor is being restarted by its own supervision strategy.
+    Restarting {
+        /// The actor being restarted.
+        id: ActorId,
+        /// Type name of the actor.
+        actor_type: &'static str,
+        /// Restarts so far, not counting this one.
+        restarts: u64,
+        /// [`Backoff`] delay before the new instance is created.
+        delay: Duration,
+    },
+    /// An actor exhausted its restart budget and escalated to its parent.
+    Escalated(ChildFailure),
+    /// An actor did not stop in time and its task was aborted.
+    Aborted(AbortedActor),
+}
 
-    /// Spawn a child actor with the default supervision
-    /// ([`SupervisionStrategy::default`]).
-    ///
-    /// The child is linked to this actor's cancellation scope: if the parent
-    /// stops the child is also cancelled. When the child exhausts its restart
-    /// budget the parent's [`child_escalated`](Actor::child_escalated) hook is
-    /// invoked.
-    pub fn spawn<B, F>(&self, factory: F) -> Addr<B>
-    where
-        F: FnMut() -> B + Send + 'static,
-        B: Actor,
-    {
-        self.spawn_with_config(factory, SupervisionStrategy::default())
+/// Receives the [`FailureEvent`]s of every actor in the system.
+///
+/// Install one with [`ActorSystem::set_failure_reporter`]. Reporters are
+/// called synchronously from the failing actor's task, so they should hand
+/// off anything slow. Closures taking `&FailureEvent` implement this trait.
+pub trait FailureReporter: Send + Sync + 'static {
+    /// Handle a single failure event.
+    fn report(&self, event: &FailureEvent);
+}
+
+impl<F> FailureReporter for F
+where
+    F: Fn(&FailureEvent) + Send + Sync + 'static,
+{
+    fn report(&self, event: &FailureEvent) {
+        self(event)
     }
+}
 
-    /// Spawn a child actor with a custom supervision strategy.
-    ///
-    /// See [`spawn`](Ctx::spawn) for details. Use this method when you need
-    /// to override the default [`SupervisionStrategy`].
-    pub fn spawn_with_config<B, F>(&self, factory: F, config: SupervisionStrategy) -> Addr<B>
-    where
-        F: FnMut() -> B + Send + 'static,
-        B: Actor,
-    {
-        let child = start_actor(
-            factory,
-            self.cancel.child_token(),
-            self.child_escalations.clone(),
-            config,
-        );
-        self.children.lock().unwrap().push(Arc::new(child.clone()));
-        child.address()
+/// Writes failure events to stderr.
+///
+/// The default reporter unless the `tracing` feature is enabled.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StderrReporter;
+
+impl FailureReporter for StderrReporter {
+    fn report(&self, event: &FailureEvent) {
+        match event {
+            FailureEvent::Panicked {
+                actor_type, panic, ..
+            } => eprintln!(
+                "ACTOR PANIC!\n actor:{}\n reason: {}\n restarting...",
+                actor_type,
+                panic.message()
+            ),
+            FailureEvent::Restarting {
+                actor_type,
+                restarts,
+                delay,
+                ..
+            } => eprintln!(
+                "Restarting actor {} in {:?} (restart {}).",
+                actor_type,
+                delay,
+                restarts + 1
+            ),
+            FailureEvent::Escalated(failure) => eprintln!(
+                "Actor {} restarted {} times, escalating.",
+                failure.actor_type, failure.restarts
+            ),
+            FailureEvent::Aborted(aborted) => eprintln!(
+                "Actor {} did not stop within {:?}, aborting.",
+                aborted.actor_type, aborted.timeout
+            ),
+        }
     }
 }
 
-/// Interface for objects that can be stopped and awaited for shutdown.
-#[async_trait]
-pub trait Stoppable {
-    /// Signal the object to stop. This is non-blocking.
-    fn stop(&self);
-    /// Wait until the object has fully stopped.
-    async fn wait_until_stopped(&self);
-    /// Signal all children to stop and wait for them to finish.
-    async fn stop_all_children(&self);
+/// Emits failure events as [`tracing`](https://docs.rs/tracing) events.
+///
+/// Panics and escalations are logged at `ERROR`, restarts at `WARN`. This is
+/// the default reporter when the `tracing` feature is enabled.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TracingReporter;
+
+#[cfg(feature = "tracing")]
+impl FailureReporter for TracingReporter {
+    fn report(&self, event: &FailureEvent) {
+        match event {
+            FailureEvent::Panicked {
+                id,
+                actor_type,
+                panic,
+            } => tracing::error!(
+                actor.id = ?id,
+                actor.kind = actor_type,
+                message.kind = panic.message_type(),
+                panic = panic.message(),
+                "actor panicked"
+            ),
+            FailureEvent::Restarting {
+                id,
+                actor_type,
+                restarts,
+                delay,
+            } => tracing::warn!(
+                actor.id = ?id,
+                actor.kind = actor_type,
+                restarts,
+                delay = ?delay,
+                "restarting actor"
+            ),
+            FailureEvent::Escalated(failure) => tracing::error!(
+                actor.id = ?failure.id,
+                actor.kind = failure.actor_type,
+                restarts = failure.restarts,
+                panic = failure.panic.as_deref(),
+                "actor exhausted its restart budget, escalating"
+            ),
+            FailureEvent::Aborted(aborted) => tracing::error!(
+                actor.id = ?aborted.id,
+                actor.kind = aborted.actor_type,
+                timeout = ?aborted.timeout,
+                "actor did not stop in time, aborting"
+            ),
+        }
+    }
 }
 
-/// Trait implemented by all message types.
-///
-/// `Message` defines the response type associated with a message. Messages
-/// must be [`Send`] and `'static`.
-///
-/// # Derive macro
-///
-/// The `tactix_macros` crate provides a convenience derive macro:
-///
-/// ```rust,ignore
-/// use tactix::Message;
-///
-/// #[derive(Message)]
-/// struct Ping;                       // Response = ()
-///
-/// #[derive(Message)]
-/// #[response(String)]
-/// struct GetName;                    // Response = String
-/// ```
-pub trait Message: Send + 'static {
-    /// The type returned by the handler for this message (use `()` for
-    /// fire-and-forget messages).
-    type Response: Send;
+/// Distribution of handler durations in fixed, exponentially sized buckets.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg(feature = "metrics")]
+pub struct Histogram {
+    buckets: [u64; Histogram::BOUNDS.len() + 1],
+    sum: Duration,
+    max: Duration,
 }
 
-/// Trait implemented on an [`Actor`] to process a specific [`Message`] type.
-///
-/// Each actor can implement `Handler` for any number of message types, each
-/// with its own response type.
-///
-/// # Async
-///
-/// Handlers are async and receive a borrow to the actor's [`Ctx`], allowing
-/// them to spawn children or send messages to other actors while handling.
-pub trait Handler<M>
-where
-    Self: Actor,
-    M: Message,
-{
-    /// Process an incoming message and return a response.
-    fn handle(&mut self, msg: M, ctx: &Ctx<Self>) -> impl Future<Output = M::Response> + Send;
+#[cfg(feature = "metrics")]
+impl Histogram {
+    /// Inclusive upper bounds of every bucket but the last, which holds all
+    /// longer durations.
+    pub const BOUNDS: [Duration; 7] = [
+        Duration::from_micros(10),
+        Duration::from_micros(100),
+        Duration::from_millis(1),
+        Duration::from_millis(10),
+        Duration::from_millis(100),
+        Duration::from_secs(1),
+        Duration::from_secs(10),
+    ];
+
+    /// Add a single observation.
+    pub fn record(&mut self, duration: Duration) {
+        let bucket = Self::BOUNDS.partition_point(|bound| *bound < duration);
+        self.buckets[bucket] += 1;
+        self.sum += duration;
+        self.max = self.max.max(duration);
+    }
+
+    /// Observation counts per bucket, matching [`BOUNDS`](Histogram::BOUNDS)
+    /// followed by the overflow bucket.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Total number of observations.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Sum of all observations.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Longest observation.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Average observation, or zero if there are none.
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.count()) {
+            Ok(0) => Duration::ZERO,
+            Ok(count) => self.sum / count,
+            Err(_) => Duration::from_secs_f64(self.sum.as_secs_f64() / self.count() as f64),
+        }
+    }
 }
 
-/// Internal trait for type-erased message dispatch inside the actor task.
+/// Point-in-time metrics for one actor, returned by [`Addr::stats`] and
+/// collected by [`MetricsRegistry`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg(feature = "metrics")]
+pub struct ActorStats {
+    /// The actor these stats describe.
+    pub id: ActorId,
+    /// Type name of the actor.
+    pub actor_type: &'static str,
+    /// Messages queued and not yet taken by the actor.
+    pub mailbox_len: usize,
+    /// Messages whose handler ran to completion.
+    pub processed: u64,
+    /// Handler panics.
+    pub panics: u64,
+    /// Times the actor was restarted, for any reason.
+    pub restarts: u64,
+    /// Handler durations keyed by message type name.
+    pub handlers: HashMap<&'static str, Histogram>,
+}
+
+#[cfg(feature = "metrics")]
+impl ActorStats {
+    fn new(id: ActorId, actor_type: &'static str) -> Self {
+        Self {
+            id,
+            actor_type,
+            mailbox_len: 0,
+            processed: 0,
+            panics: 0,
+            restarts: 0,
+            handlers: HashMap::new(),
+        }
+    }
+}
+
+/// A metrics observation handed to the system's [`MetricsExporter`].
+#[derive(Clone, Debug)]
+#[cfg(feature = "metrics")]
+pub enum MetricsEvent {
+    /// A handler ran to completion.
+    Handled {
+        /// The actor that handled the message.
+        id: ActorId,
+        /// Type name of the actor.
+        actor_type: &'static str,
+        /// Type name of the message.
+        message_type: &'static str,
+        /// Time spent in the handler.
+        duration: Duration,
+        /// Messages still queued after this one was taken.
+        mailbox_len: usize,
+    },
+    /// A handler panicked.
+    Panicked {
+        /// The actor whose handler panicked.
+        id: ActorId,
+        /// Type name of the actor.
+        actor_type: &'static str,
+    },
+    /// The actor was restarted.
+    Restarted {
+        /// The restarted actor.
+        id: ActorId,
+        /// Type name of the actor.
+        actor_type: &'static str,
+    },
+}
+
+/// Receives [`MetricsEvent`]s from every actor, for forwarding to a metrics
+/// backend.
 ///
-/// You should not need to implement this trait directly; it is automatically
-/// implemented via [`Envelope`] for any pair that satisfies
-/// `A: Actor + Handler<M>`.
-#[async_trait]
-pub trait ActorMessage<A: Actor>: Send {
-    async fn process(&mut self, actor: &mut A, ctx: &Ctx<A>);
+/// Install one with [`ActorSystem::set_metrics_exporter`]; none is installed
+/// by default. Like [`FailureReporter`]s, exporters run on the actor's task
+/// and must be cheap.
+#[cfg(feature = "metrics")]
+pub trait MetricsExporter: Send + Sync + 'static {
+    /// Record a single observation.
+    fn export(&self, event: &MetricsEvent);
 }
 
-/// Wraps a message payload with an optional oneshot channel for the response.
+/// In-process [`MetricsExporter`] that aggregates events into per-actor
+/// [`ActorStats`].
 ///
-/// When a response channel is provided the handler's return value is sent back
-/// over it. When `tx` is `None` the message is fire-and-forget.
-pub struct Envelope<M>
-where
-    M: Message,
-{
-    pub msg: Option<M>,
-    pub tx: Option<oneshot::Sender<M::Response>>,
+/// Clones share the same underlying registry, so keep one to query after
+/// installing another.
+#[derive(Clone, Debug, Default)]
+#[cfg(feature = "metrics")]
+pub struct MetricsRegistry {
+    actors: Arc<Mutex<HashMap<ActorId, ActorStats>>>,
 }
 
-impl<M> Envelope<M>
-where
-    M: Message,
-{
-    /// Creates a new `Envelope`, boxed, ready to send over the actor's channel.
-    pub fn new(msg: Option<M>, tx: Option<oneshot::Sender<M::Response>>) -> Box<Self> {
-        Box::new(Self { msg, tx })
+#[cfg(feature = "metrics")]
+impl MetricsRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Aggregated stats for one actor, if it has reported anything.
+    pub fn get(&self, id: ActorId) -> Option<ActorStats> {
+        self.actors.lock().unwrap().get(&id).cloned()
+    }
+
+    /// Aggregated stats for every actor that has reported anything.
+    pub fn snapshot(&self) -> Vec<ActorStats> {
+        self.actors.lock().unwrap().values().cloned().collect()
     }
 }
 
-#[async_trait]
-impl<A, M> ActorMessage<A> for Envelope<M>
-where
-    A: Actor + Handler<M>,
-    M: Message,
-{
-    async fn process(&mut self, act: &mut A, ctx: &Ctx<A>) {
-        if let Some(msg) = self.msg.take() {
-            let res = act.handle(msg, ctx).await;
-            if let Some(tx) = self.tx.take() {
-                let _ = tx.send(res);
+#[cfg(feature = "metrics")]
+impl MetricsExporter for MetricsRegistry {
+    fn export(&self, event: &MetricsEvent) {
+        let mut actors = self.actors.lock().unwrap();
+        match *event {
+            MetricsEvent::Handled {
+                id,
+                actor_type,
+                message_type,
+                duration,
+                mailbox_len,
+            } => {
+                let stats = actors
+                    .entry(id)
+                    .or_insert_with(|| ActorStats::new(id, actor_type));
+                stats.processed += 1;
+                stats.mailbox_len = mailbox_len;
+                stats
+                    .handlers
+                    .entry(message_type)
+                    .or_default()
+                    .record(duration);
+            }
+            MetricsEvent::Panicked { id, actor_type } => {
+                actors
+                    .entry(id)
+                    .or_insert_with(|| ActorStats::new(id, actor_type))
+                    .panics += 1;
+            }
+            MetricsEvent::Restarted { id, actor_type } => {
+                actors
+                    .entry(id)
+                    .or_insert_with(|| ActorStats::new(id, actor_type))
+                    .restarts += 1;
+            }
+        }
+    }
+}
+
+/// Live counters behind [`Addr::stats`], shared by an actor's task and its
+/// addresses.
+#[derive(Default)]
+#[cfg(feature = "metrics")]
+struct Metrics {
+    processed: AtomicU64,
+    panics: AtomicU64,
+    restarts: AtomicU64,
+    handlers: Mutex<HashMap<&'static str, Histogram>>,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    fn handled<A: Actor>(&self, ctx: &Ctx<A>, message_type: &'static str, duration: Duration) {
+        self.processed.fetch_add(1, Ordering::Relaxed);
+        self.handlers
+            .lock()
+            .unwrap()
+            .entry(message_type)
+            .or_default()
+            .record(duration);
+        ctx.addr.system.export(MetricsEvent::Handled {
+            id: ctx.id,
+            actor_type: std::any::type_name::<A>(),
+            message_type,
+            duration,
+            mailbox_len: ctx.addr.tx.len(),
+        });
+    }
+
+    fn panicked<A: Actor>(&self, ctx: &Ctx<A>) {
+        self.panics.fetch_add(1, Ordering::Relaxed);
+        ctx.addr.system.export(MetricsEvent::Panicked {
+            id: ctx.id,
+            actor_type: std::any::type_name::<A>(),
+        });
+    }
+
+    fn restarted<A: Actor>(&self, ctx: &Ctx<A>) {
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+        ctx.addr.system.export(MetricsEvent::Restarted {
+            id: ctx.id,
+            actor_type: std::any::type_name::<A>(),
+        });
+    }
+}
+
+impl ActorSystem {
+    /// Start a new, independent actor system and return its root context.
+    ///
+    /// Each system has its own actor tree, dead letters, failure reporter and
+    /// metrics exporter. Spawn into it with [`Ctx::spawn`] or
+    /// [`Actor::start_in`], and stop it with [`Ctx::shutdown`]; dropping the
+    /// context does not stop the system.
+    pub fn new(config: ActorSystemConfig) -> Ctx<ActorSystem> {
+        ActorSystemBuilder::from_config(config).build()
+    }
+
+    /// Returns a builder for a new, independent actor system.
+    pub fn builder() -> ActorSystemBuilder {
+        ActorSystemBuilder::new()
+    }
+
+    /// Returns a reference to the global `ActorSystem` context, initialising it
+    /// with the default [`ActorSystemConfig`] on first call.
+    pub fn global() -> &'static Ctx<ActorSystem> {
+        ACTOR_SYSTEM.get_or_init(|| Self::new(ActorSystemConfig::default()))
+    }
+
+    /// Returns the address of the global `ActorSystem`.
+    ///
+    /// Use this to send messages (e.g. [`Shutdown`]) to the system from
+    /// anywhere in your application.
+    pub fn addr() -> Addr<ActorSystem> {
+        Self::global().address()
+    }
+
+    /// Gracefully shut down the global actor system.
+    ///
+    /// See [`Ctx::shutdown`].
+    pub async fn shutdown() -> ShutdownReport {
+        Self::global().shutdown().await
+    }
+
+    /// Find a named actor in the global system by path.
+    ///
+    /// See [`Ctx::lookup`].
+    pub fn lookup<A: Actor>(path: &str) -> Option<Addr<A>> {
+        Self::global().lookup(path)
+    }
+
+    /// Describe every live actor in the global system.
+    ///
+    /// See [`Ctx::snapshot_tree`].
+    pub fn snapshot_tree() -> ActorSnapshot {
+        Self::global().snapshot_tree()
+    }
+
+    /// Select actors in the global system by path.
+    ///
+    /// See [`Ctx::select`].
+    pub fn select(pattern: &str) -> ActorSelection {
+        Self::global().select(pattern)
+    }
+
+    /// Run the global system until SIGINT or SIGTERM, then shut it down.
+    ///
+    /// See [`Ctx::run_until_signal`].
+    pub async fn run_until_signal() -> io::Result<ShutdownReport> {
+        Self::global().run_until_signal().await
+    }
+
+    /// Register a task to run when the global system shuts down.
+    ///
+    /// See [`Ctx::add_shutdown_task`].
+    pub fn add_shutdown_task(
+        phase: ShutdownPhase,
+        name: impl Into<String>,
+        task: impl Future<Output = ()> + Send + 'static,
+    ) {
+        Self::global().add_shutdown_task(phase, name, task);
+    }
+
+    /// Subscribe to the global system's dead letters.
+    ///
+    /// See [`Ctx::dead_letters`].
+    pub fn dead_letters() -> broadcast::Receiver<DeadLetter> {
+        Self::global().dead_letters()
+    }
+
+    /// Replace the global system's [`FailureReporter`].
+    ///
+    /// See [`Ctx::set_failure_reporter`].
+    pub fn set_failure_reporter(reporter: impl FailureReporter) {
+        Self::global().set_failure_reporter(reporter);
+    }
+
+    /// Install the global system's [`MetricsExporter`].
+    ///
+    /// See [`Ctx::set_metrics_exporter`].
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics_exporter(exporter: impl MetricsExporter) {
+        Self::global().set_metrics_exporter(exporter);
+    }
+}
+
+impl Ctx<ActorSystem> {
+    /// Gracefully shut down this actor system.
+    ///
+    /// Shutdown proceeds in phases:
+    ///
+    /// 1. Tasks registered for [`ShutdownPhase::BeforeActorsStop`] run.
+    /// 2. [`Shutdown`] is sent to the system, which finishes processing
+    ///    pending messages, stops all children, and exits. Children that do
+    ///    not stop within their [`stop_timeout`](ActorConfig::stop_timeout)
+    ///    are aborted.
+    /// 3. Tasks registered for [`ShutdownPhase::AfterActorsStop`] run.
+    ///
+    /// If the system was configured with a
+    /// [`shutdown_timeout`](ActorSystemConfig::shutdown_timeout), each phase
+    /// is cut short once it elapses: unfinished tasks are abandoned and every
+    /// actor still running is aborted. The returned report lists both.
+    pub async fn shutdown(&self) -> ShutdownReport {
+        let system = &self.addr.system;
+        let mut timed_out_tasks = system
+            .run_shutdown_phase(ShutdownPhase::BeforeActorsStop)
+            .await;
+
+        *system.aborted.lock().unwrap() = Some(Vec::new());
+        self.address().tell(Shutdown);
+        match system.config.shutdown_timeout {
+            Some(timeout) => {
+                if tokio::time::timeout(timeout, self.stopped.cancelled())
+                    .await
+                    .is_err()
+                {
+                    Supervised::abort(self, timeout);
+                }
             }
+            None => self.stopped.cancelled().await,
+        }
+        let aborted = system.aborted.lock().unwrap().take().unwrap_or_default();
+
+        timed_out_tasks.extend(
+            system
+                .run_shutdown_phase(ShutdownPhase::AfterActorsStop)
+                .await,
+        );
+        ShutdownReport {
+            aborted,
+            timed_out_tasks,
         }
     }
-}
 
-/// Address of an actor. Messages are sent through this handle.
-///
-/// `Addr` is cheaply cloneable and can be used to:
-/// - Fire-and-forget a message via [`tell`](Sender::tell).
-/// - Await a response via [`ask`](Sender::ask).
-/// - Create a type-erased [`Recipient`] via [`recipient`](Sender::recipient).
-pub struct Addr<A>
-where
-    A: Actor,
-{
-    tx: mpsc::UnboundedSender<PointerToActorMessage<A>>,
-    stopped: CancellationToken,
-}
+    /// Wait for SIGINT or SIGTERM (Ctrl-C on non-Unix platforms), then
+    /// gracefully [`shutdown`](Ctx::shutdown) this system.
+    ///
+    /// A second signal during shutdown aborts every actor still running and
+    /// returns immediately; shutdown tasks still running are abandoned. The
+    /// actors aborted this way are reported with a zero timeout.
+    ///
+    /// ```rust,no_run
+    /// # #[tokio::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// // ... start actors ...
+    /// let report = tactix::ActorSystem::run_until_signal().await?;
+    /// if !report.is_clean() {
+    ///     eprintln!("unclean shutdown: {report:?}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Fails if the signal handlers cannot be installed.
+    pub async fn run_until_signal(&self) -> io::Result<ShutdownReport> {
+        let signals = Signals::new()?.into_stream();
+        Ok(self.run_until(signals).await)
+    }
+
+    /// Shut down once `signals` yields, aborting every actor if it yields
+    /// again before the shutdown has finished.
+    async fn run_until(&self, signals: impl Stream<Item = ()>) -> ShutdownReport {
+        tokio::pin!(signals);
+        signals.next().await;
+        let shutdown = self.shutdown();
+        tokio::pin!(shutdown);
+        tokio::select! {
+            report = &mut shutdown => report,
+            Some(()) = signals.next() => ShutdownReport {
+                aborted: self.abort_all(),
+                timed_out_tasks: Vec::new(),
+            },
+        }
+    }
+
+    /// Abort every actor in the system without waiting for it to stop.
+    fn abort_all(&self) -> Vec<AbortedActor> {
+        let system = &self.addr.system;
+        system.aborted.lock().unwrap().get_or_insert_with(Vec::new);
+        Supervised::abort(self, Duration::ZERO);
+        system.aborted.lock().unwrap().take().unwrap_or_default()
+    }
+
+    /// Describe every live actor in this system, starting from its root.
+    ///
+    /// Each actor is inspected in turn without pausing the others, so the
+    /// tree is not an atomic snapshot of the whole system.
+    pub fn snapshot_tree(&self) -> ActorSnapshot {
+        Supervised::snapshot(self, None)
+    }
+
+    /// Register a task to run during [`shutdown`](Ctx::shutdown).
+    ///
+    /// The task is not polled until its `phase` begins. `name` identifies it
+    /// in the [`ShutdownReport`] should it time out.
+    pub fn add_shutdown_task(
+        &self,
+        phase: ShutdownPhase,
+        name: impl Into<String>,
+        task: impl Future<Output = ()> + Send + 'static,
+    ) {
+        self.addr
+            .system
+            .shutdown_tasks
+            .lock()
+            .unwrap()
+            .push((phase, name.into(), Box::pin(task)));
+    }
+
+    /// Subscribe to this system's dead letters.
+    ///
+    /// Only letters published after subscribing are received. A subscriber
+    /// that falls behind by more than
+    /// [`dead_letter_capacity`](ActorSystemConfig::dead_letter_capacity)
+    /// letters skips the oldest ones and observes
+    /// [`RecvError::Lagged`](broadcast::error::RecvError::Lagged).
+    pub fn dead_letters(&self) -> broadcast::Receiver<DeadLetter> {
+        self.addr.system.dead_letters.subscribe()
+    }
+
+    /// Replace the reporter that receives the [`FailureEvent`]s of every actor
+    /// in this system.
+    pub fn set_failure_reporter(&self, reporter: impl FailureReporter) {
+        *self.addr.system.failure_reporter.write().unwrap() = Arc::new(reporter);
+    }
+
+    /// Install the exporter that receives the [`MetricsEvent`]s of every
+    /// actor in this system, replacing any previous one.
+    ///
+    /// Per-actor stats are always available from [`Addr::stats`]; an
+    /// exporter is only needed to aggregate or ship them elsewhere.
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics_exporter(&self, exporter: impl MetricsExporter) {
+        *self.addr.system.metrics_exporter.write().unwrap() = Some(Arc::new(exporter));
+    }
+}
+
+type PointerToActorMessage<A> = Box<dyn ActorMessage<A>>;
+
+/// Trait implemented by all actors.
+///
+/// Actors are long-lived, stateful objects that communicate via message passing.
+/// Each actor runs in its own Tokio task and processes messages sequentially.
+///
+/// # Lifecycle
+///
+/// 1. [`started`](Actor::started) is called once before the first message.
+/// 2. Messages are handled one-by-one via [`Handler`] implementations.
+/// 3. If a handler panics, the actor restarts according to [`SupervisionStrategy`];
+///    [`pre_restart`](Actor::pre_restart) is called on the failing instance
+///    before it is replaced and [`restarted`](Actor::restarted) on the new one.
+/// 4. When stopped for any [`StopReason`] — e.g. via [`Ctx::stop`], a panic
+///    under `SupervisionStrategy::NoRestart` or an exhausted restart budget —
+///    [`stopped`](Actor::stopped) is called and the task exits.
+pub trait Actor: Send + Sized + 'static {
+    /// Spawn this actor on the global system and return its address.
+    ///
+    /// The actor uses the system's default [`ActorConfig`]. Since there is
+    /// only this one instance, a restart hands the same instance back to
+    /// the actor rather than building a fresh one, see
+    /// [`Ctx::spawn_with_handoff`].
+    ///
+    /// To configure supervision or spawn as a child of another actor, use
+    /// [`Ctx::spawn`] or [`Ctx::spawn_with_config`] instead.
+    fn start(self) -> Addr<Self> {
+        self.start_in(ActorSystem::global())
+    }
+    /// Like [`start`](Actor::start), but spawns onto `system`, e.g. one
+    /// created with [`ActorSystem::new`].
+    fn start_in(self, system: &Ctx<ActorSystem>) -> Addr<Self> {
+        let mut once = Some(self);
+        system.spawn_with_handoff(
+            move |previous| {
+                previous
+                    .or_else(|| once.take())
+                    .expect("the instance is handed back on restart")
+            },
+            ActorConfig::default(),
+        )
+    }
+    /// Called after the actor task starts, before any messages are processed.
+    fn started(&mut self, _ctx: &Ctx<Self>) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+    /// Called after the actor has stopped (all messages drained, children
+    /// stopped) and the task is about to exit.
+    ///
+    /// `reason` explains why the actor stopped; the same reason is available
+    /// afterwards from [`Addr::stop_reason`].
+    fn stopped(
+        &mut self,
+        _reason: &StopReason,
+        _ctx: &Ctx<Self>,
+    ) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+    /// Called on the failing instance right after a handler panics, when the
+    /// actor is supervised with [`SupervisionStrategy::Restart`].
+    ///
+    /// It runs before the actor's children are stopped, before any
+    /// [backoff](Backoff) delay and before escalating to the parent, so it is
+    /// called even if the actor ends up stopped instead of replaced.
+    ///
+    /// Use it to flush buffers, release external resources or record the
+    /// faulting message. It is not called for restarts that were not caused by
+    /// a panic, such as a parent restarting its children.
+    fn pre_restart(
+        &mut self,
+        _panic: &PanicInfo,
+        _ctx: &Ctx<Self>,
+    ) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+    /// Called after a restart before the first message is processed.
+    ///
+    /// `restarts` is the total number of restarts that have occurred.
+    fn restarted(&mut self, _restarts: u64, _ctx: &Ctx<Self>) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+    /// Called when a child actor has escalated after exhausting its restart
+    /// budget, unless this actor was spawned with a [`ChildStrategy`] other
+    /// than [`ChildStrategy::Escalate`].
+    ///
+    /// `failure` identifies the child and describes why it failed. Return
+    /// `Some(Interrupt)` to influence the parent's behaviour (default:
+    /// [`Interrupt::RestartToEscalate`]), or `None` to ignore the escalation.
+    fn child_escalated(
+        &mut self,
+        _failure: &ChildFailure,
+        _ctx: &Ctx<Self>,
+    ) -> impl Future<Output = Option<Interrupt>> + Send {
+        async { Some(Interrupt::RestartToEscalate) }
+    }
+    /// Declares the messages this actor can be sent through
+    /// [`ActorSelection::recipients`], which reaches actors without knowing
+    /// their type. Called once, synchronously, when the actor is spawned.
+    ///
+    /// Actors expose nothing by default.
+    fn expose(_exposed: &mut Exposed<Self>) {}
+}
+
+/// Details of a panic raised while an actor handled a message.
+#[derive(Clone, Debug)]
+pub struct PanicInfo {
+    message: String,
+    message_type: &'static str,
+}
+
+impl PanicInfo {
+    fn new(payload: &(dyn Any + Send), message_type: &'static str) -> Self {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str()))
+            .unwrap_or("<non-string panic>")
+            .to_owned();
+        Self {
+            message,
+            message_type,
+        }
+    }
+
+    /// The panic message, or `<non-string panic>` if the panic payload was
+    /// neither a `&str` nor a `String`.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Type name of the message whose handler panicked.
+    pub fn message_type(&self) -> &'static str {
+        self.message_type
+    }
+}
+
+/// Outcome of an actor's event loop iteration that drives lifecycle decisions.
+pub enum Interrupt {
+    /// Gracefully stop the actor.
+    Stop,
+    /// Restart the actor and, if the restart budget is exhausted, escalate
+    /// to the parent.
+    RestartToEscalate,
+    /// Restart the actor without counting towards its restart budget.
+    Restart,
+}
+
+/// Supervision strategy that controls how panics in an actor are handled.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SupervisionStrategy {
+    /// Do not restart on panic. The actor task exits immediately with no
+    /// escalation to the parent.
+    NoRestart,
+    /// Restart up to `max_restarts` times within any sliding `window`.
+    ///
+    /// A failure that would exceed the budget is not restarted; instead the
+    /// actor escalates to its parent, which applies its [`ChildStrategy`].
+    ///
+    /// With a `backoff` each restart is delayed; messages keep queueing in
+    /// the mailbox in the meantime. Without one the actor restarts
+    /// immediately.
+    Restart {
+        window: Duration,
+        max_restarts: u64,
+        backoff: Option<Backoff>,
+    },
+}
+
+impl Default for SupervisionStrategy {
+    fn default() -> Self {
+        Self::Restart {
+            window: Duration::from_secs(5),
+            max_restarts: 3,
+            backoff: None,
+        }
+    }
+}
+
+/// Exponential backoff applied between restarts.
+///
+/// The first restart waits `min`; each consecutive restart within the
+/// supervision window waits `multiplier` times longer than the previous one,
+/// up to `max`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Backoff {
+    /// Delay before the first restart.
+    pub min: Duration,
+    /// Upper bound on the delay between restarts.
+    pub max: Duration,
+    /// Factor the delay grows by with each consecutive restart.
+    pub multiplier: f64,
+    /// Fraction between `0.0` and `1.0` by which each delay is randomly
+    /// shortened, so actors failing together do not restart in lockstep.
+    pub jitter: Option<f64>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            min: Duration::from_millis(100),
+            max: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before the `attempt`th consecutive restart, counting from 1.
+    fn delay(&self, attempt: u64) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u64) as i32;
+        let delay =
+            Duration::try_from_secs_f64(self.min.as_secs_f64() * self.multiplier.powi(exponent))
+                .unwrap_or(self.max)
+                .min(self.max);
+        match self.jitter {
+            Some(jitter) => delay.mul_f64(1.0 - jitter.clamp(0.0, 1.0) * random_fraction()),
+            None => delay,
+        }
+    }
+}
+
+/// Returns a random number in `[0, 1)` without pulling in an RNG crate.
+fn random_fraction() -> f64 {
+    let bits = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Policy a parent applies to its children when one of them escalates.
+///
+/// A child escalates once it has exhausted the restart budget of its own
+/// [`SupervisionStrategy`]. It then waits for its parent to decide its fate,
+/// with new messages continuing to queue in its mailbox.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChildStrategy {
+    /// Defer to the parent's [`child_escalated`](Actor::child_escalated)
+    /// hook (default). Unless the hook restarts or stops the parent, the
+    /// escalated child is stopped.
+    #[default]
+    Escalate,
+    /// Restart only the child that escalated.
+    OneForOne,
+    /// Restart every child of the parent.
+    OneForAll,
+    /// Restart the child that escalated and every child spawned after it.
+    RestForOne,
+}
+
+/// Mailbox type used to queue messages for an actor.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mailbox {
+    /// Queue an unlimited number of messages (default).
+    #[default]
+    Unbounded,
+    /// Queue at most the given number of messages.
+    ///
+    /// When the mailbox is full [`Sender::send`] waits for capacity,
+    /// [`Sender::try_tell`] returns [`SendError::Full`] and
+    /// [`Sender::tell`] drops the message.
+    Bounded(NonZeroUsize),
+}
+
+/// Configuration applied when spawning an actor with
+/// [`Ctx::spawn_with_config`].
+///
+/// Settings left as `None` fall back to the defaults of the actor's system,
+/// see [`ActorSystemBuilder`]. A bare [`SupervisionStrategy`] converts into
+/// an `ActorConfig` with the system's default [`Mailbox`], so existing
+/// `spawn_with_config` calls keep working.
+#[derive(Clone, Debug, Default)]
+pub struct ActorConfig {
+    /// How the actor reacts to panics in its handlers.
+    pub supervision: Option<SupervisionStrategy>,
+    /// The type of mailbox messages are queued in.
+    pub mailbox: Option<Mailbox>,
+    /// How the actor treats its own children when one of them escalates.
+    pub child_strategy: ChildStrategy,
+    /// How long the actor's parent waits for it, and its children, to stop
+    /// before aborting its task.
+    pub stop_timeout: Option<Duration>,
+}
+
+/// Unique identifier assigned to every actor when it is spawned.
+///
+/// Identifiers are never reused within a process and stay the same when the
+/// actor restarts, so they can key maps of actors and appear in logs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ActorId(u64);
+
+/// Describes a child that escalated after exhausting its restart budget.
+///
+/// Passed to the parent's [`child_escalated`](Actor::child_escalated) hook.
+#[derive(Clone, Debug)]
+pub struct ChildFailure {
+    /// Identity of the child, matching [`Addr::id`] of its address.
+    pub id: ActorId,
+    /// Type name of the child actor.
+    pub actor_type: &'static str,
+    /// Message of the last panic, or `None` if the child escalated for
+    /// another reason, such as one of its own children escalating.
+    pub panic: Option<String>,
+    /// Total number of times the child has been restarted.
+    pub restarts: u64,
+}
+
+/// Displays the bare number, e.g. `42`.
+impl fmt::Display for ActorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ActorId {
+    fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl From<SupervisionStrategy> for ActorConfig {
+    fn from(supervision: SupervisionStrategy) -> Self {
+        Self {
+            supervision: Some(supervision),
+            ..Default::default()
+        }
+    }
+}
+
+/// Sending half of an actor's mailbox.
+struct MailboxTx<A: Actor> {
+    channel: TxChannel<A>,
+    /// The receiving half, only used to report the mailbox length.
+    rx: Weak<Mutex<RxChannel<A>>>,
+}
+
+enum TxChannel<A: Actor> {
+    Unbounded(mpsc::UnboundedSender<PointerToActorMessage<A>>),
+    Bounded(mpsc::Sender<PointerToActorMessage<A>>),
+}
+
+impl<A: Actor> Clone for MailboxTx<A> {
+    fn clone(&self) -> Self {
+        let channel = match &self.channel {
+            TxChannel::Unbounded(tx) => TxChannel::Unbounded(tx.clone()),
+            TxChannel::Bounded(tx) => TxChannel::Bounded(tx.clone()),
+        };
+        Self {
+            channel,
+            rx: self.rx.clone(),
+        }
+    }
+}
+
+impl<A: Actor> MailboxTx<A> {
+    /// Queue a message without waiting, returning it when it cannot be queued.
+    fn try_send(
+        &self,
+        msg: PointerToActorMessage<A>,
+    ) -> Result<(), SendError<PointerToActorMessage<A>>> {
+        match &self.channel {
+            TxChannel::Unbounded(tx) => tx.send(msg).map_err(|e| SendError::Closed(e.0)),
+            TxChannel::Bounded(tx) => tx.try_send(msg).map_err(|e| match e {
+                mpsc::error::TrySendError::Full(msg) => SendError::Full(msg),
+                mpsc::error::TrySendError::Closed(msg) => SendError::Closed(msg),
+            }),
+        }
+    }
+
+    /// Queue a message, waiting for capacity if the mailbox is bounded.
+    async fn send(
+        &self,
+        msg: PointerToActorMessage<A>,
+    ) -> Result<(), SendError<PointerToActorMessage<A>>> {
+        match &self.channel {
+            TxChannel::Unbounded(tx) => tx.send(msg).map_err(|e| SendError::Closed(e.0)),
+            TxChannel::Bounded(tx) => tx.send(msg).await.map_err(|e| SendError::Closed(e.0)),
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        match &self.channel {
+            TxChannel::Unbounded(tx) => tx.is_closed(),
+            TxChannel::Bounded(tx) => tx.is_closed(),
+        }
+    }
+
+    fn downgrade(&self) -> WeakMailboxTx<A> {
+        let channel = match &self.channel {
+            TxChannel::Unbounded(tx) => WeakTxChannel::Unbounded(tx.downgrade()),
+            TxChannel::Bounded(tx) => WeakTxChannel::Bounded(tx.downgrade()),
+        };
+        WeakMailboxTx {
+            channel,
+            rx: self.rx.clone(),
+        }
+    }
+
+    /// Number of messages queued and not yet received by the actor.
+    #[cfg(feature = "metrics")]
+    fn len(&self) -> usize {
+        queued(&self.rx)
+    }
+}
+
+/// Sending half of an actor's mailbox that does not keep it open.
+struct WeakMailboxTx<A: Actor> {
+    channel: WeakTxChannel<A>,
+    rx: Weak<Mutex<RxChannel<A>>>,
+}
+
+enum WeakTxChannel<A: Actor> {
+    Unbounded(mpsc::WeakUnboundedSender<PointerToActorMessage<A>>),
+    Bounded(mpsc::WeakSender<PointerToActorMessage<A>>),
+}
+
+impl<A: Actor> Clone for WeakMailboxTx<A> {
+    fn clone(&self) -> Self {
+        let channel = match &self.channel {
+            WeakTxChannel::Unbounded(tx) => WeakTxChannel::Unbounded(tx.clone()),
+            WeakTxChannel::Bounded(tx) => WeakTxChannel::Bounded(tx.clone()),
+        };
+        Self {
+            channel,
+            rx: self.rx.clone(),
+        }
+    }
+}
+
+impl<A: Actor> WeakMailboxTx<A> {
+    /// Returns a sending half that keeps the mailbox open, unless every
+    /// other one has already been dropped.
+    fn upgrade(&self) -> Option<MailboxTx<A>> {
+        let channel = match &self.channel {
+            WeakTxChannel::Unbounded(tx) => TxChannel::Unbounded(tx.upgrade()?),
+            WeakTxChannel::Bounded(tx) => TxChannel::Bounded(tx.upgrade()?),
+        };
+        Some(MailboxTx {
+            channel,
+            rx: self.rx.clone(),
+        })
+    }
+
+    /// Number of messages queued and not yet received by the actor.
+    fn len(&self) -> usize {
+        queued(&self.rx)
+    }
+}
+
+/// Number of messages waiting in the receiving half, or `0` once the actor
+/// task has finished.
+fn queued<A: Actor>(rx: &Weak<Mutex<RxChannel<A>>>) -> usize {
+    rx.upgrade().map_or(0, |rx| match &*rx.lock().unwrap() {
+        RxChannel::Unbounded(rx) => rx.len(),
+        RxChannel::Bounded(rx) => rx.len(),
+    })
+}
+
+/// Receiving half of an actor's mailbox, owned by the actor task.
+///
+/// The channel sits behind a lock only so that senders can read its length;
+/// the task takes the lock once per poll, and it is otherwise uncontended.
+struct MailboxRx<A: Actor> {
+    channel: Arc<Mutex<RxChannel<A>>>,
+}
+
+enum RxChannel<A: Actor> {
+    Unbounded(mpsc::UnboundedReceiver<PointerToActorMessage<A>>),
+    Bounded(mpsc::Receiver<PointerToActorMessage<A>>),
+}
+
+impl<A: Actor> MailboxRx<A> {
+    async fn recv(&self) -> Option<PointerToActorMessage<A>> {
+        future::poll_fn(|cx| match &mut *self.channel.lock().unwrap() {
+            RxChannel::Unbounded(rx) => rx.poll_recv(cx),
+            RxChannel::Bounded(rx) => rx.poll_recv(cx),
+        })
+        .await
+    }
+
+    fn try_recv(&self) -> Option<PointerToActorMessage<A>> {
+        match &mut *self.channel.lock().unwrap() {
+            RxChannel::Unbounded(rx) => rx.try_recv().ok(),
+            RxChannel::Bounded(rx) => rx.try_recv().ok(),
+        }
+    }
+
+    fn close(&self) {
+        match &mut *self.channel.lock().unwrap() {
+            RxChannel::Unbounded(rx) => rx.close(),
+            RxChannel::Bounded(rx) => rx.close(),
+        }
+    }
+}
+
+fn mailbox<A: Actor>(mailbox: Mailbox) -> (MailboxTx<A>, MailboxRx<A>) {
+    let (tx, rx) = match mailbox {
+        Mailbox::Unbounded => {
+            let (tx, rx) = mpsc::unbounded_channel();
+            (TxChannel::Unbounded(tx), RxChannel::Unbounded(rx))
+        }
+        Mailbox::Bounded(capacity) => {
+            let (tx, rx) = mpsc::channel(capacity.get());
+            (TxChannel::Bounded(tx), RxChannel::Bounded(rx))
+        }
+    };
+    let rx = Arc::new(Mutex::new(rx));
+    (
+        MailboxTx {
+            channel: tx,
+            rx: Arc::downgrade(&rx),
+        },
+        MailboxRx { channel: rx },
+    )
+}
+
+/// Records why an actor task ended and signals its linked actors, even if
+/// the task unwinds from a panicking lifecycle hook or is dropped before
+/// finishing.
+///
+/// Must be dropped before the actor's `stopped` token is cancelled.
+struct ExitGuard {
+    stop_reason: Arc<Mutex<Option<StopReason>>>,
+    links: Arc<Links>,
+    /// Set once the task has finished normally.
+    reason: Option<StopReason>,
+}
+
+impl Drop for ExitGuard {
+    fn drop(&mut self) {
+        let reason = match self.reason.take() {
+            Some(reason) => reason,
+            None if std::thread::panicking() => StopReason::Panicked,
+            // The task was dropped, e.g. after being aborted.
+            None => StopReason::Aborted,
+        };
+        *self.stop_reason.lock().unwrap() = Some(reason.clone());
+        self.links.exit(&reason);
+    }
+}
+
+fn start_actor<A, F>(
+    mut factory: F,
+    id: ActorId,
+    path: Arc<str>,
+    cancel: CancellationToken,
+    escalate_to_parent: mpsc::UnboundedSender<ChildFailure>,
+    config: ActorConfig,
+    system: Arc<SystemState>,
+) -> (Ctx<A>, Addr<A>)
+where
+    A: Actor,
+    F: FnMut(Option<A>) -> A + Send + 'static,
+{
+    let ActorConfig {
+        supervision,
+        mailbox: mailbox_config,
+        child_strategy,
+        stop_timeout,
+    } = config;
+    let restart_config = supervision.unwrap_or_else(|| system.config.supervision.clone());
+    let mailbox_config = mailbox_config.unwrap_or(system.config.mailbox);
+    let stop_timeout = stop_timeout.or(system.config.stop_timeout);
+    let (tx, rx) = mailbox::<A>(mailbox_config);
+    let (child_escalations, mut child_escalations_rx) = mpsc::unbounded_channel();
+    let (restart, mut restart_rx) = mpsc::unbounded_channel();
+    let stopped = CancellationToken::new();
+    let stop_reason = Arc::new(Mutex::new(None));
+    let links = Arc::new(Links {
+        id,
+        cancel: cancel.clone(),
+        stop_reason: stop_reason.clone(),
+        peers: Mutex::new(Some(HashMap::new())),
+        trap: Mutex::new(None),
+    });
+    let addr = Addr {
+        id,
+        path,
+        tx,
+        stopped: stopped.clone(),
+        stop_reason: stop_reason.clone(),
+        links,
+        #[cfg(feature = "metrics")]
+        metrics: Arc::new(Metrics::default()),
+        system,
+    };
+    let ctx = Ctx::<A> {
+        id,
+        addr: addr.downgrade(),
+        children: Arc::new(Mutex::new(Vec::new())),
+        watching: Arc::new(Mutex::new(HashMap::new())),
+        cancel,
+        stopped: stopped.clone(),
+        stop_reason,
+        stop_timeout,
+        task: Arc::new(OnceLock::new()),
+        status: Arc::default(),
+        exposed: Arc::new(Exposed::new()),
+        child_escalations,
+        restart,
+    };
+    let ctx_loop = ctx.clone();
+    let task = ctx.addr.system.clone().spawn(async move {
+        let mut is_restart = false;
+        let ctx = ctx_loop;
+        let system = ctx.addr.system.clone();
+        let _stopped_guard = stopped.drop_guard();
+        let mut exit = ExitGuard {
+            stop_reason: ctx.stop_reason.clone(),
+            links: ctx.addr.links.clone(),
+            reason: None,
+        };
+
+        let mut restarts = 0u64;
+        let mut last_panic = None;
+        // Restarts within the current supervision window, oldest first.
+        let mut recent_restarts = VecDeque::<Instant>::new();
+        let mut previous = None;
+        let (mut actor, reason) = loop {
+            ctx.set_state(ActorState::Starting);
+            let mut actor = factory(previous.take());
+            actor.started(&ctx).await;
+            if is_restart {
+                #[cfg(feature = "metrics")]
+                ctx.addr.metrics.restarted(&ctx);
+                actor.restarted(restarts, &ctx).await;
+            }
+            ctx.set_state(ActorState::Running);
+            let code = loop {
+                tokio::select! {
+                    biased; // Important makes sure we check in order
+
+                    // Check the cancel signal
+                    _ = ctx.cancel.cancelled() => {
+                        break Interrupt::Stop;
+                    }
+
+                    // Check if our children have escalated
+                    Some(failure) = child_escalations_rx.recv() => {
+                        if child_strategy != ChildStrategy::Escalate {
+                            ctx.restart_children(failure.id, child_strategy);
+                        } else if let Some(interrupt) = actor.child_escalated(&failure, &ctx).await {
+                            if matches!(interrupt, Interrupt::Stop) {
+                                ctx.request_stop(StopReason::Stopped);
+                            }
+                            last_panic = None;
+                            break interrupt;
+                        } else {
+                            ctx.stop_child(failure.id);
+                        }
+                    }
+
+                    // Check if our parent has asked us to restart
+                    Some(()) = restart_rx.recv() => {
+                        break Interrupt::Restart;
+                    }
+
+                    // Receive a message
+                    msg = rx.recv() => {
+                        let Some(mut msg) = msg else {
+                            ctx.request_stop(StopReason::AddressesDropped);
+                            break Interrupt::Stop;
+                        };
+                        // Skip work the sender has already given up waiting for.
+                        if msg.is_expired() {
+                            system.dead_letter(msg.dead_letter(DeadLetterReason::Expired));
+                            continue;
+                        }
+                        #[cfg(feature = "metrics")]
+                        let started = Instant::now();
+                        let result = AssertUnwindSafe(msg.process(&mut actor, &ctx))
+                            .catch_unwind()
+                            .await;
+                        if let Err(panic) = result {
+                            #[cfg(feature = "metrics")]
+                            ctx.addr.metrics.panicked(&ctx);
+                            msg.fail(AskError::Panicked);
+                            let panic = PanicInfo::new(&*panic, msg.message_type());
+                            system.report(FailureEvent::Panicked {
+                                id: ctx.id,
+                                actor_type: std::any::type_name::<A>(),
+                                panic: panic.clone(),
+                            });
+                            last_panic = Some(panic);
+                            break Interrupt::RestartToEscalate;
+                        }
+                        #[cfg(feature = "metrics")]
+                        ctx.addr.metrics.handled(&ctx, msg.message_type(), started.elapsed());
+                    }
+                }
+            };
+
+            // Refuse new messages while winding down so senders find out
+            // immediately instead of queueing into a mailbox nobody reads.
+            if matches!(code, Interrupt::Stop) {
+                ctx.set_state(ActorState::Stopping);
+                rx.close();
+            } else {
+                ctx.set_state(ActorState::Restarting);
+            }
+
+            // Let the failing instance clean up before anything can cancel
+            // its restart: a backoff, an escalation or a stop request.
+            if let (Interrupt::RestartToEscalate, SupervisionStrategy::Restart { .. }, Some(panic)) =
+                (&code, &restart_config, &last_panic)
+            {
+                actor.pre_restart(panic, &ctx).await;
+            }
+
+            // Stop and wait for all children regardless of why we exited.
+            ctx.stop_all_children().await;
+
+            match code {
+                Interrupt::Stop => {
+                    break (actor, ctx.requested_stop_reason());
+                }
+                Interrupt::Restart => {
+                    previous = Some(actor);
+                    is_restart = true;
+                    restarts += 1;
+                    ctx.status.lock().unwrap().restarts = restarts;
+                }
+                Interrupt::RestartToEscalate => {
+                    match &restart_config {
+                        SupervisionStrategy::NoRestart => {
+                            // Unsupervised: just exit, do not escalate.
+                            break (actor, StopReason::Panicked);
+                        }
+                        SupervisionStrategy::Restart {
+                            window,
+                            max_restarts,
+                            backoff,
+                        } => {
+                            let now = Instant::now();
+                            while recent_restarts
+                                .front()
+                                .is_some_and(|at| now.duration_since(*at) > *window)
+                            {
+                                recent_restarts.pop_front();
+                            }
+                            if recent_restarts.len() as u64 >= *max_restarts {
+                                let failure = ChildFailure {
+                                    id: ctx.id,
+                                    actor_type: std::any::type_name::<A>(),
+                                    panic: last_panic
+                                        .as_ref()
+                                        .map(|panic| panic.message().to_owned()),
+                                    restarts,
+                                };
+                                system.report(FailureEvent::Escalated(failure.clone()));
+                                if escalate_to_parent.send(failure).is_err() {
+                                    break (actor, StopReason::RestartsExhausted);
+                                }
+                                // Wait for the parent to restart or stop us.
+                                tokio::select! {
+                                    biased;
+                                    _ = ctx.cancel.cancelled() => break (actor, StopReason::RestartsExhausted),
+                                    Some(()) = restart_rx.recv() => recent_restarts.clear(),
+                                    else => break (actor, StopReason::RestartsExhausted),
+                                }
+                            } else {
+                                recent_restarts.push_back(now);
+                                let delay = backoff.as_ref().map_or(Duration::ZERO, |backoff| {
+                                    backoff.delay(recent_restarts.len() as u64)
+                                });
+                                system.report(FailureEvent::Restarting {
+                                    id: ctx.id,
+                                    actor_type: std::any::type_name::<A>(),
+                                    restarts,
+                                    delay,
+                                });
+                                if !delay.is_zero() {
+                                    tokio::select! {
+                                        biased;
+                                        _ = ctx.cancel.cancelled() => break (actor, ctx.requested_stop_reason()),
+                                        _ = tokio::time::sleep(delay) => {}
+                                    }
+                                }
+                            }
+                            previous = Some(actor);
+                            is_restart = true;
+                            restarts += 1;
+                            ctx.status.lock().unwrap().restarts = restarts;
+                        }
+                    }
+                }
+            }
+
+            // Restarts requested while this one was underway, e.g. by a
+            // sibling escalating under `OneForAll` during our backoff, are
+            // merged into it rather than restarting the new instance again.
+            while restart_rx.try_recv().is_ok() {}
+        };
+        ctx.set_state(ActorState::Stopping);
+        actor.stopped(&reason, &ctx).await;
+
+        // Anything still queued will never be handled.
+        rx.close();
+        while let Some(mut msg) = rx.try_recv() {
+            msg.fail(AskError::Stopped);
+            system.dead_letter(msg.dead_letter(DeadLetterReason::Unprocessed));
+        }
+        exit.reason = Some(reason);
+        // `exit` drops first, then _stopped_guard signals `stopped`.
+    });
+    let _ = ctx.task.set(task.abort_handle());
+    (ctx, addr)
+}
+
+/// Context handle for an actor.
+///
+/// Provides access to the actor's address and the ability to spawn child actors.
+/// Every [`Handler`] receives a reference to `Ctx` so actors can supervise their
+/// children.
+///
+/// Cloning `Ctx` is cheap (it uses `Arc` internally).
+pub struct Ctx<A: Actor> {
+    id: ActorId,
+    addr: WeakAddr<A>,
+    children: Arc<Children>,
+    watching: Arc<Mutex<HashMap<ActorId, CancellationToken>>>,
+    cancel: CancellationToken,
+    stopped: CancellationToken,
+    stop_reason: Arc<Mutex<Option<StopReason>>>,
+    stop_timeout: Option<Duration>,
+    task: Arc<OnceLock<tokio::task::AbortHandle>>,
+    status: Arc<Mutex<Status>>,
+    exposed: Arc<Exposed<A>>,
+    child_escalations: mpsc::UnboundedSender<ChildFailure>,
+    restart: mpsc::UnboundedSender<()>,
+}
+
+impl<A: Actor> Clone for Ctx<A> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            addr: self.addr.clone(),
+            children: self.children.clone(),
+            watching: self.watching.clone(),
+            cancel: self.cancel.clone(),
+            stopped: self.stopped.clone(),
+            stop_reason: self.stop_reason.clone(),
+            stop_timeout: self.stop_timeout,
+            task: self.task.clone(),
+            status: self.status.clone(),
+            exposed: self.exposed.clone(),
+            child_escalations: self.child_escalations.clone(),
+            restart: self.restart.clone(),
+        }
+    }
+}
+
+impl<A: Actor> Ctx<A> {
+    /// Returns the address of this actor.
+    ///
+    /// Once every other address has been dropped the actor is stopping with
+    /// [`StopReason::AddressesDropped`], and the returned address is closed.
+    #[must_use]
+    pub fn address(&self) -> Addr<A> {
+        self.addr.upgrade().unwrap_or_else(|| self.addr.closed())
+    }
+
+    /// Returns the identifier of this actor.
+    pub fn id(&self) -> ActorId {
+        self.id
+    }
+
+    /// Returns this actor's path in the actor tree, such as
+    /// `/system/orders/worker-3`.
+    ///
+    /// The path extends the parent's path with the actor's name if it was
+    /// [spawned with one](Ctx::spawn_named), or with `$` and its [`ActorId`]
+    /// otherwise. The system's root actor is `/system`.
+    pub fn path(&self) -> &str {
+        &self.addr.path
+    }
+
+    /// Spawn a child actor with its system's default [`ActorConfig`].
+    ///
+    /// Unless configured otherwise with [`ActorSystemBuilder`], children use
+    /// [`SupervisionStrategy::default`] and an unbounded [`Mailbox`].
+    ///
+    /// The child is linked to this actor's cancellation scope: if the parent
+    /// stops the child is also cancelled. The child also stops, with
+    /// [`StopReason::AddressesDropped`], once every [`Addr`] to it has been
+    /// dropped; its parent does not keep it running. When the child exhausts its restart
+    /// budget the parent applies its [`ChildStrategy`], which by default
+    /// invokes the parent's [`child_escalated`](Actor::child_escalated) hook.
+    pub fn spawn<B, F>(&self, factory: F) -> Addr<B>
+    where
+        F: FnMut() -> B + Send + 'static,
+        B: Actor,
+    {
+        self.spawn_with_config(factory, ActorConfig::default())
+    }
+
+    /// Spawn a child actor with a custom configuration.
+    ///
+    /// See [`spawn`](Ctx::spawn) for details. Use this method when you need
+    /// to override the default [`SupervisionStrategy`] or [`Mailbox`]; either
+    /// a bare `SupervisionStrategy` or a full [`ActorConfig`] is accepted.
+    pub fn spawn_with_config<B, F>(&self, mut factory: F, config: impl Into<ActorConfig>) -> Addr<B>
+    where
+        F: FnMut() -> B + Send + 'static,
+        B: Actor,
+    {
+        self.spawn_with_handoff(move |_| factory(), config)
+    }
+
+    /// Spawn a child actor whose factory is handed the previous instance on
+    /// restart.
+    ///
+    /// The factory receives `None` on first start and `Some(previous)` each
+    /// time the actor is restarted, so known-good state such as counters or
+    /// caches can be carried over (or extracted into a snapshot) while the
+    /// rest is rebuilt. After a panic the previous instance may be left
+    /// half-updated by the failing handler, so only keep what is safe to
+    /// reuse. Otherwise behaves like [`spawn_with_config`](Ctx::spawn_with_config).
+    pub fn spawn_with_handoff<B, F>(&self, factory: F, config: impl Into<ActorConfig>) -> Addr<B>
+    where
+        F: FnMut(Option<B>) -> B + Send + 'static,
+        B: Actor,
+    {
+        self.spawn_child(factory, config.into(), None)
+    }
+
+    fn spawn_child<B, F>(&self, factory: F, config: ActorConfig, name: Option<&str>) -> Addr<B>
+    where
+        F: FnMut(Option<B>) -> B + Send + 'static,
+        B: Actor,
+    {
+        let id = ActorId::next();
+        let path = match name {
+            Some(name) => format!("{}/{}", self.addr.path, name),
+            None => format!("{}/${}", self.addr.path, id),
+        };
+        let (child, addr) = start_actor(
+            factory,
+            id,
+            path.into(),
+            self.cancel.child_token(),
+            self.child_escalations.clone(),
+            config,
+            self.addr.system.clone(),
+        );
+        self.children.lock().unwrap().push(Arc::new(child));
+        addr
+    }
+
+    /// Spawn a child actor named `name`, registered in its system so that it
+    /// can be found with [`lookup`](Ctx::lookup).
+    ///
+    /// The name forms the last segment of the actor's [`path`](Ctx::path)
+    /// and only has to be unique among this actor's children, so
+    /// `/system/eu/worker-1` and `/system/us/worker-1` can both exist. The
+    /// name is released when the actor stops; restarts keep it. The
+    /// registry holds an address to the actor,
+    /// so unlike an unnamed actor it keeps running after every address
+    /// returned to callers has been dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`NameError::Invalid`] if `name` is empty, contains `/` or
+    /// `*`, or starts with `$`, and [`NameError::Taken`] if a running child
+    /// of this actor already has the name.
+    pub fn spawn_named<B, F>(
+        &self,
+        name: impl Into<String>,
+        factory: F,
+    ) -> Result<Addr<B>, NameError>
+    where
+        F: FnMut() -> B + Send + 'static,
+        B: Actor,
+    {
+        self.spawn_named_with_config(name, factory, ActorConfig::default())
+    }
+
+    /// Like [`spawn_named`](Ctx::spawn_named), with a custom configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`NameError`] if the name is invalid or taken, see
+    /// [`spawn_named`](Ctx::spawn_named).
+    pub fn spawn_named_with_config<B, F>(
+        &self,
+        name: impl Into<String>,
+        mut factory: F,
+        config: impl Into<ActorConfig>,
+    ) -> Result<Addr<B>, NameError>
+    where
+        F: FnMut() -> B + Send + 'static,
+        B: Actor,
+    {
+        let name = name.into();
+        if name.is_empty() || name.contains(['/', '*']) || name.starts_with('$') {
+            return Err(NameError::Invalid(name));
+        }
+        let path = format!("{}/{}", self.addr.path, name);
+        let system = &self.addr.system;
+        let mut registry = system.registry.lock().unwrap();
+        if let Some(holder) = registry.get(&path) {
+            if !holder.stopped.is_cancelled() {
+                return Err(NameError::Taken {
+                    name,
+                    holder: holder.id,
+                });
+            }
+        }
+        let addr = self.spawn_child(move |_| factory(), config.into(), Some(&name));
+        registry.insert(
+            path.clone(),
+            Registered {
+                id: addr.id,
+                stopped: addr.stopped.clone(),
+                addr: Box::new(addr.clone()),
+            },
+        );
+        drop(registry);
+
+        let (id, stopped, state) = (addr.id, addr.stopped.clone(), system.clone());
+        system.spawn(async move {
+            stopped.cancelled().await;
+            let mut registry = state.registry.lock().unwrap();
+            // The name may already have been taken over by a new actor.
+            if registry.get(&path).is_some_and(|entry| entry.id == id) {
+                registry.remove(&path);
+            }
+        });
+        Ok(addr)
+    }
+
+    /// Select actors in this actor's system by path.
+    ///
+    /// Patterns starting with `/` are absolute; others are relative to this
+    /// actor, so `ctx.select("*")` selects its children. See
+    /// [`ActorSelection`] for the wildcards supported.
+    pub fn select(&self, pattern: &str) -> ActorSelection {
+        let pattern = if pattern.starts_with('/') {
+            pattern.to_owned()
+        } else {
+            format!("{}/{}", self.addr.path, pattern)
+        };
+        ActorSelection {
+            pattern,
+            root: self.addr.system.root.get().cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Find a running actor spawned with [`spawn_named`](Ctx::spawn_named)
+    /// in this actor's system by its path.
+    ///
+    /// Paths starting with `/` are absolute; others are relative to this
+    /// actor, as with [`select`](Ctx::select). So on the system
+    /// `lookup("orders")` finds the top-level actor named `orders`, and
+    /// within `orders` it finds a child.
+    ///
+    /// Returns `None` if no running named actor has the path, or if it is
+    /// not a `B`.
+    pub fn lookup<B: Actor>(&self, path: &str) -> Option<Addr<B>> {
+        let path = if path.starts_with('/') {
+            path.to_owned()
+        } else {
+            format!("{}/{}", self.addr.path, path)
+        };
+        let registry = self.addr.system.registry.lock().unwrap();
+        let entry = registry.get(&path)?;
+        if entry.stopped.is_cancelled() {
+            return None;
+        }
+        entry.addr.downcast_ref::<Addr<B>>().cloned()
+    }
+
+    /// Restart the escalated child and, depending on `strategy`, its
+    /// siblings.
+    fn restart_children(&self, failed: ActorId, strategy: ChildStrategy) {
+        let children = self.children.lock().unwrap();
+        let Some(position) = children.iter().position(|child| child.id() == failed) else {
+            return;
+        };
+        let affected = match strategy {
+            ChildStrategy::Escalate | ChildStrategy::OneForOne => &children[position..=position],
+            ChildStrategy::OneForAll => &children[..],
+            ChildStrategy::RestForOne => &children[position..],
+        };
+        for child in affected {
+            child.restart();
+        }
+    }
+
+    /// Stop an escalated child that is not going to be restarted.
+    fn stop_child(&self, failed: ActorId) {
+        self.children.lock().unwrap().retain(|child| {
+            if child.id() == failed {
+                child.stop_with(StopReason::RestartsExhausted);
+            }
+            child.id() != failed
+        });
+    }
+
+    /// Start watching another actor.
+    ///
+    /// When the watched actor stops, a [`Terminated`] message is delivered to
+    /// this actor's [`Handler<Terminated>`]. Watching an actor that has
+    /// already stopped delivers `Terminated` right away. Watching the same
+    /// actor twice has no additional effect.
+    pub fn watch<B: Actor>(&self, addr: &Addr<B>)
+    where
+        A: Handler<Terminated>,
+    {
+        let unwatch = self.stopped.child_token();
+        {
+            let mut watching = self.watching.lock().unwrap();
+            if watching.contains_key(&addr.id) {
+                return;
+            }
+            watching.insert(addr.id, unwatch.clone());
+        }
+        // Neither side's mailbox is kept open by watching.
+        let watched = addr.downgrade();
+        let watcher = self.clone();
+        self.addr.system.spawn(async move {
+            tokio::select! {
+                _ = unwatch.cancelled() => {}
+                _ = watched.stopped.cancelled() => {
+                    watcher.watching.lock().unwrap().remove(&watched.id);
+                    // The exit guard records a reason before `stopped`
+                    // fires; fall back to the task having been dropped.
+                    let reason = watched
+                        .stop_reason
+                        .lock()
+                        .unwrap()
+                        .clone()
+                        .unwrap_or(StopReason::Aborted);
+                    let Some(addr) = watcher.addr.upgrade() else {
+                        return;
+                    };
+                    // Wait for room rather than drop the notification if the
+                    // mailbox is full.
+                    let _ = addr
+                        .send(Terminated {
+                            id: watched.id,
+                            reason,
+                        })
+                        .await;
+                }
+            }
+        });
+    }
+
+    /// Stop watching another actor. No [`Terminated`] message is delivered
+    /// for it afterwards unless it is watched again.
+    pub fn unwatch<B: Actor>(&self, addr: &Addr<B>) {
+        if let Some(unwatch) = self.watching.lock().unwrap().remove(&addr.id) {
+            unwatch.cancel();
+        }
+    }
+
+    fn set_state(&self, state: ActorState) {
+        self.status.lock().unwrap().state = state;
+    }
+
+    /// Record why this actor is stopping, unless a reason was already given.
+    fn request_stop(&self, reason: StopReason) {
+        self.stop_reason.lock().unwrap().get_or_insert(reason);
+    }
+
+    /// The reason recorded by [`request_stop`](Ctx::request_stop), defaulting
+    /// to the parent having stopped us through our cancellation token.
+    fn requested_stop_reason(&self) -> StopReason {
+        self.stop_reason
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or(StopReason::ParentStopped)
+    }
+
+    fn stop_with(&self, reason: StopReason) {
+        self.addr.links.stop(reason);
+    }
+
+    /// Link this actor with another so that they exit together.
+    ///
+    /// Links are bidirectional: when either actor exits abnormally (see
+    /// [`StopReason::is_abnormal`]) the other is stopped with
+    /// [`StopReason::Linked`], unless it [traps exits](Ctx::trap_exit).
+    /// Linking to an actor that has already stopped signals its exit
+    /// immediately. Links survive restarts.
+    pub fn link<B: Actor>(&self, addr: &Addr<B>) {
+        let me = &self.addr.links;
+        let peer = &addr.links;
+        if me.id == peer.id {
+            return;
+        }
+        if let Some(peers) = me.peers.lock().unwrap().as_mut() {
+            peers.insert(peer.id, peer.clone());
+        } else {
+            return;
+        }
+        let linked = match peer.peers.lock().unwrap().as_mut() {
+            Some(peers) => {
+                peers.insert(me.id, me.clone());
+                true
+            }
+            None => false,
+        };
+        if !linked {
+            let reason = addr
+                .stop_reason
+                .lock()
+                .unwrap()
+                .clone()
+                .expect("stop reason is recorded before links are closed");
+            me.signal(peer.id, &reason);
+        }
+    }
+
+    /// Remove a link created with [`link`](Ctx::link).
+    pub fn unlink<B: Actor>(&self, addr: &Addr<B>) {
+        if let Some(peers) = self.addr.links.peers.lock().unwrap().as_mut() {
+            peers.remove(&addr.id);
+        }
+        if let Some(peers) = addr.links.peers.lock().unwrap().as_mut() {
+            peers.remove(&self.id);
+        }
+    }
+
+    /// Choose whether exits of linked actors stop this actor (the default) or
+    /// are delivered to its [`Handler<Exited>`] as [`Exited`] messages.
+    ///
+    /// While trapping, an `Exited` message is delivered for every exit of a
+    /// linked actor, including normal ones.
+    pub fn trap_exit(&self, trap: bool)
+    where
+        A: Handler<Exited>,
+    {
+        let handler: Option<ExitHandler> = if trap {
+            let addr = self.addr.clone();
+            Some(Box::new(move |exited| {
+                // Wait for room rather than drop the signal if the mailbox is
+                // full.
+                if let Some(addr) = addr.upgrade() {
+                    addr.system.clone().spawn(async move {
+                        let _ = addr.send(exited).await;
+                    });
+                }
+            }))
+        } else {
+            None
+        };
+        *self.addr.links.trap.lock().unwrap() = handler;
+    }
+}
+
+type ExitHandler = Box<dyn Fn(Exited) + Send + Sync>;
+
+/// Exit signalling shared by an actor's [`Ctx`] and every [`Addr`] to it.
+struct Links {
+    id: ActorId,
+    cancel: CancellationToken,
+    stop_reason: Arc<Mutex<Option<StopReason>>>,
+    /// Linked actors, or `None` once this actor has exited.
+    peers: Mutex<Option<HashMap<ActorId, Arc<Links>>>>,
+    /// Delivers exit signals as [`Exited`] messages while trapping exits.
+    trap: Mutex<Option<ExitHandler>>,
+}
+
+impl Links {
+    fn stop(&self, reason: StopReason) {
+        self.stop_reason.lock().unwrap().get_or_insert(reason);
+        self.cancel.cancel();
+    }
+
+    /// Handle the exit of the linked actor `from`.
+    fn signal(&self, from: ActorId, reason: &StopReason) {
+        match self.peers.lock().unwrap().as_mut() {
+            Some(peers) => peers.remove(&from),
+            None => return,
+        };
+        if let Some(trap) = &*self.trap.lock().unwrap() {
+            trap(Exited {
+                id: from,
+                reason: reason.clone(),
+            });
+        } else if reason.is_abnormal() {
+            self.stop(StopReason::Linked(from));
+        }
+    }
+
+    /// Signal every linked actor that this actor has exited.
+    fn exit(&self, reason: &StopReason) {
+        // Dropping the trap handler and peers also breaks reference cycles.
+        self.trap.lock().unwrap().take();
+        let peers = self.peers.lock().unwrap().take().unwrap_or_default();
+        for peer in peers.into_values() {
+            peer.signal(self.id, reason);
+        }
+    }
+}
+
+/// Why an actor stopped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The actor was stopped through [`Stoppable::stop`], or its
+    /// [`child_escalated`](Actor::child_escalated) hook returned
+    /// [`Interrupt::Stop`].
+    Stopped,
+    /// The actor's parent stopped or restarted, taking its children with it.
+    ParentStopped,
+    /// Every [`Addr`] to the actor was dropped.
+    AddressesDropped,
+    /// A handler panicked and the actor's [`SupervisionStrategy`] does not
+    /// restart it, or one of the actor's lifecycle hooks panicked.
+    Panicked,
+    /// The actor exhausted its restart budget and was not restarted by its
+    /// parent.
+    RestartsExhausted,
+    /// An actor [linked](Ctx::link) to this one exited abnormally.
+    Linked(ActorId),
+    /// The actor did not stop within its
+    /// [`stop_timeout`](ActorConfig::stop_timeout) and its task was aborted
+    /// without calling [`Actor::stopped`].
+    Aborted,
+}
+
+impl StopReason {
+    /// Returns `true` for exits caused by a failure, which propagate across
+    /// [links](Ctx::link).
+    pub fn is_abnormal(&self) -> bool {
+        matches!(
+            self,
+            Self::Panicked | Self::RestartsExhausted | Self::Linked(_) | Self::Aborted
+        )
+    }
+}
+
+/// Delivered to an actor that [traps exits](Ctx::trap_exit) when an actor
+/// [linked](Ctx::link) to it exits.
+#[derive(Message, Clone, Debug)]
+pub struct Exited {
+    /// Identity of the actor that exited.
+    pub id: ActorId,
+    /// Why it exited.
+    pub reason: StopReason,
+}
+
+/// Delivered to an actor when an actor it [watches](Ctx::watch) stops.
+#[derive(Message, Clone, Debug)]
+pub struct Terminated {
+    /// Identity of the actor that stopped.
+    pub id: ActorId,
+    /// Why it stopped.
+    pub reason: StopReason,
+}
+
+/// Lifecycle stage of a live actor, as reported by [`ActorSnapshot`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ActorState {
+    /// The actor is being created or is running its
+    /// [`started`](Actor::started) or [`restarted`](Actor::restarted) hook.
+    #[default]
+    Starting,
+    /// The actor is processing messages.
+    Running,
+    /// The actor is stopping its children, backing off or awaiting its
+    /// parent's verdict before being restarted.
+    Restarting,
+    /// The actor is stopping its children or running its
+    /// [`stopped`](Actor::stopped) hook.
+    Stopping,
+}
+
+/// A point-in-time view of a live actor and its descendants, returned by
+/// [`Ctx::snapshot_tree`].
+///
+/// With the `serde` feature it implements `serde::Serialize`, so it can be
+/// dumped as JSON from a debug endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ActorSnapshot {
+    /// Identity of the actor.
+    pub id: ActorId,
+    /// Name given to [`Ctx::spawn_named`], or `system` for the root actor.
+    pub name: Option<String>,
+    /// The actor's [path](Ctx::path).
+    pub path: String,
+    /// Type name of the actor.
+    pub actor_type: &'static str,
+    /// The actor's parent, or `None` for the root actor.
+    pub parent: Option<ActorId>,
+    /// Messages waiting in the mailbox.
+    pub mailbox_len: usize,
+    /// Times the actor has been restarted.
+    pub restarts: u64,
+    /// What the actor is doing.
+    pub state: ActorState,
+    /// Live children, in spawn order.
+    pub children: Vec<ActorSnapshot>,
+}
+
+impl ActorSnapshot {
+    /// Iterate over this actor and all its descendants, parents first.
+    pub fn iter(&self) -> impl Iterator<Item = &ActorSnapshot> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || {
+            let next = stack.pop()?;
+            stack.extend(next.children.iter().rev());
+            Some(next)
+        })
+    }
+}
+
+/// An actor's lifecycle, shared with [`ActorSnapshot`]s.
+#[derive(Default)]
+struct Status {
+    state: ActorState,
+    restarts: u64,
+}
+
+type Children = Mutex<Vec<Arc<dyn Supervised>>>;
+
+/// A parent's view of one of its children.
+trait Supervised: Stoppable + Send + Sync {
+    fn id(&self) -> ActorId;
+    fn path(&self) -> &str;
+    fn is_alive(&self) -> bool;
+    /// The child's `WeakAddr<A>`.
+    fn addr(&self) -> &dyn Any;
+    /// A `Recipient<M>` for the child if it [exposes](Actor::expose) the
+    /// message type `M` with the given id and is still running.
+    fn recipient(&self, message: TypeId) -> Option<Box<dyn Any>>;
+    fn children(&self) -> Vec<Arc<dyn Supervised>>;
+    /// Describe the child and its live descendants.
+    fn snapshot(&self, parent: Option<ActorId>) -> ActorSnapshot;
+    /// Ask the child to restart without counting towards its restart budget.
+    fn restart(&self);
+    /// Ask the child to stop, recording why.
+    fn stop_with(&self, reason: StopReason);
+    /// How long to wait for the child to stop before aborting it.
+    fn stop_timeout(&self) -> Option<Duration>;
+    /// Abort the child's task, and those of its children, after it exceeded
+    /// `timeout` while stopping.
+    fn abort(&self, timeout: Duration);
+}
+
+impl<A: Actor> Supervised for Ctx<A> {
+    fn id(&self) -> ActorId {
+        self.id
+    }
+
+    fn path(&self) -> &str {
+        &self.addr.path
+    }
+
+    fn is_alive(&self) -> bool {
+        !self.stopped.is_cancelled()
+    }
+
+    fn addr(&self) -> &dyn Any {
+        &self.addr
+    }
+
+    fn recipient(&self, message: TypeId) -> Option<Box<dyn Any>> {
+        let make = self.exposed.recipients.get(&message)?;
+        make(&self.addr)
+    }
+
+    fn children(&self) -> Vec<Arc<dyn Supervised>> {
+        self.children.lock().unwrap().clone()
+    }
+
+    fn snapshot(&self, parent: Option<ActorId>) -> ActorSnapshot {
+        let (state, restarts) = {
+            let status = self.status.lock().unwrap();
+            (status.state, status.restarts)
+        };
+        let name = self
+            .addr
+            .path
+            .rsplit('/')
+            .next()
+            .filter(|segment| !segment.starts_with('$'))
+            .map(str::to_owned);
+        ActorSnapshot {
+            id: self.id,
+            name,
+            path: self.addr.path.to_string(),
+            actor_type: std::any::type_name::<A>(),
+            parent,
+            mailbox_len: self.addr.tx.len(),
+            restarts,
+            state,
+            children: Supervised::children(self)
+                .iter()
+                .filter(|child| child.is_alive())
+                .map(|child| child.snapshot(Some(self.id)))
+                .collect(),
+        }
+    }
+
+    fn stop_timeout(&self) -> Option<Duration> {
+        self.stop_timeout
+    }
+
+    fn abort(&self, timeout: Duration) {
+        if self.stopped.is_cancelled() {
+            return;
+        }
+        if let Some(task) = self.task.get() {
+            task.abort();
+        }
+        self.addr.system.abort(AbortedActor {
+            id: self.id,
+            actor_type: std::any::type_name::<A>(),
+            timeout,
+        });
+        // The task no longer records its own exit, so do it on its behalf.
+        *self.stop_reason.lock().unwrap() = Some(StopReason::Aborted);
+        self.addr.links.exit(&StopReason::Aborted);
+        self.stopped.cancel();
+        let children: Vec<_> = self.children.lock().unwrap().drain(..).collect();
+        for child in children {
+            child.abort(timeout);
+        }
+    }
+
+    fn stop_with(&self, reason: StopReason) {
+        Ctx::stop_with(self, reason);
+    }
+
+    fn restart(&self) {
+        let _ = self.restart.send(());
+    }
+}
+
+/// Interface for objects that can be stopped and awaited for shutdown.
+#[async_trait]
+pub trait Stoppable {
+    /// Signal the object to stop. This is non-blocking.
+    fn stop(&self);
+    /// Wait until the object has fully stopped.
+    async fn wait_until_stopped(&self);
+    /// Signal all children to stop and wait for them to finish.
+    ///
+    /// Children stop concurrently. A child that has not stopped within its
+    /// [`stop_timeout`](ActorConfig::stop_timeout) is aborted together with
+    /// its own children.
+    async fn stop_all_children(&self);
+}
+
+/// Trait implemented by all message types.
+///
+/// `Message` defines the response type associated with a message. Messages
+/// must be [`Send`] and `'static`.
+///
+/// # Derive macro
+///
+/// The `tactix_macros` crate provides a convenience derive macro:
+///
+/// ```rust,ignore
+/// use tactix::Message;
+///
+/// #[derive(Message)]
+/// struct Ping;                       // Response = ()
+///
+/// #[derive(Message)]
+/// #[response(String)]
+/// struct GetName;                    // Response = String
+/// ```
+pub trait Message: Send + 'static {
+    /// The type returned by the handler for this message (use `()` for
+    /// fire-and-forget messages).
+    type Response: Send;
+}
+
+/// Trait implemented on an [`Actor`] to process a specific [`Message`] type.
+///
+/// Each actor can implement `Handler` for any number of message types, each
+/// with its own response type.
+///
+/// # Async
+///
+/// Handlers are async and receive a borrow to the actor's [`Ctx`], allowing
+/// them to spawn children or send messages to other actors while handling.
+pub trait Handler<M>
+where
+    Self: Actor,
+    M: Message,
+{
+    /// Process an incoming message and return a response.
+    fn handle(&mut self, msg: M, ctx: &Ctx<Self>) -> impl Future<Output = M::Response> + Send;
+}
+
+/// Internal trait for type-erased message dispatch inside the actor task.
+///
+/// You should not need to implement this trait directly; it is automatically
+/// implemented via [`Envelope`] for any pair that satisfies
+/// `A: Actor + Handler<M>`.
+#[async_trait]
+pub trait ActorMessage<A: Actor>: Send {
+    async fn process(&mut self, actor: &mut A, ctx: &Ctx<A>);
+    /// Notify a waiting sender that the message will not be answered.
+    fn fail(&mut self, error: AskError);
+    /// Returns `true` once the sender's deadline for a response has passed.
+    fn is_expired(&self) -> bool;
+    /// Describe this message as undeliverable.
+    fn dead_letter(&self, reason: DeadLetterReason) -> DeadLetter;
+    /// Type name of the wrapped message.
+    fn message_type(&self) -> &'static str;
+    /// Convert back into the concrete envelope so an undelivered message can
+    /// be returned to its sender.
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send>;
+}
+
+/// Wraps a message payload with an optional oneshot channel for the response.
+///
+/// When a response channel is provided the handler's return value is sent back
+/// over it. When `tx` is `None` the message is fire-and-forget. When a
+/// `deadline` is set and has passed by the time the actor reaches the message,
+/// the message is skipped.
+///
+/// With the `tracing` feature the sender's current span is captured as well,
+/// and the handler's span is recorded as following from it. Outside this
+/// crate an `Envelope` can only be built with [`Envelope::new`], so enabling
+/// the feature never breaks code that builds one.
+#[non_exhaustive]
+pub struct Envelope<M>
+where
+    M: Message,
+{
+    pub msg: Option<M>,
+    pub tx: Option<oneshot::Sender<Result<M::Response, AskError>>>,
+    pub deadline: Option<tokio::time::Instant>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl<M> Envelope<M>
+where
+    M: Message,
+{
+    /// Creates a new `Envelope`, boxed, ready to send over the actor's channel.
+    pub fn new(
+        msg: Option<M>,
+        tx: Option<oneshot::Sender<Result<M::Response, AskError>>>,
+    ) -> Box<Self> {
+        Box::new(Self {
+            msg,
+            tx,
+            deadline: None,
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
+        })
+    }
+
+    /// Sets the point after which the sender no longer waits for a response.
+    pub fn with_deadline(mut self: Box<Self>, deadline: tokio::time::Instant) -> Box<Self> {
+        self.deadline = Some(deadline);
+        self
+    }
+}
+
+#[async_trait]
+impl<A, M> ActorMessage<A> for Envelope<M>
+where
+    A: Actor + Handler<M>,
+    M: Message,
+{
+    async fn process(&mut self, act: &mut A, ctx: &Ctx<A>) {
+        if let Some(msg) = self.msg.take() {
+            #[cfg(feature = "tracing")]
+            let res = {
+                use tracing::Instrument;
+                let span = tracing::info_span!(
+                    "handle",
+                    actor.kind = std::any::type_name::<A>(),
+                    actor.id = ?ctx.id(),
+                    message.kind = std::any::type_name::<M>(),
+                );
+                span.follows_from(&self.span);
+                act.handle(msg, ctx).instrument(span).await
+            };
+            #[cfg(not(feature = "tracing"))]
+            let res = act.handle(msg, ctx).await;
+            if let Some(tx) = self.tx.take() {
+                let _ = tx.send(Ok(res));
+            }
+        }
+    }
+
+    fn fail(&mut self, error: AskError) {
+        if let Some(tx) = self.tx.take() {
+            let _ = tx.send(Err(error));
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| deadline <= tokio::time::Instant::now())
+    }
+
+    fn dead_letter(&self, reason: DeadLetterReason) -> DeadLetter {
+        DeadLetter::new::<A, M>(reason)
+    }
+
+    fn message_type(&self) -> &'static str {
+        std::any::type_name::<M>()
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any + Send> {
+        self
+    }
+}
+
+/// Error returned when a message could not be queued in an actor's mailbox.
+///
+/// The undelivered message is handed back so the caller can retry or
+/// reroute it.
+#[derive(thiserror::Error)]
+pub enum SendError<M> {
+    /// The actor's [bounded](Mailbox::Bounded) mailbox is at capacity.
+    #[error("mailbox full")]
+    Full(M),
+    /// The actor has stopped and no longer accepts messages.
+    #[error("mailbox closed")]
+    Closed(M),
+}
+
+impl<M> SendError<M> {
+    /// Returns the undelivered message.
+    pub fn into_inner(self) -> M {
+        match self {
+            Self::Full(msg) | Self::Closed(msg) => msg,
+        }
+    }
+
+    fn map<N>(self, f: impl FnOnce(M) -> N) -> SendError<N> {
+        match self {
+            Self::Full(msg) => SendError::Full(f(msg)),
+            Self::Closed(msg) => SendError::Closed(f(msg)),
+        }
+    }
+}
+
+impl<M> fmt::Debug for SendError<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Full(_) => f.write_str("Full(..)"),
+            Self::Closed(_) => f.write_str("Closed(..)"),
+        }
+    }
+}
+
+/// Error returned by [`Ctx::spawn_named`] when the actor cannot be given the
+/// requested name. Nothing is spawned.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NameError {
+    /// Another running child of the same parent already has the name.
+    #[error("an actor named {name:?} already exists under the same parent")]
+    Taken {
+        /// The name that was requested.
+        name: String,
+        /// The actor currently registered under it.
+        holder: ActorId,
+    },
+    /// The name cannot be a path segment: it is empty, contains `/` or `*`,
+    /// or starts with `$`.
+    #[error("{0:?} is not a valid actor name")]
+    Invalid(String),
+}
+
+/// Error returned by [`Sender::try_ask`] when no response was received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum AskError {
+    /// The actor has stopped and no longer accepts messages.
+    #[error("mailbox closed")]
+    MailboxClosed,
+    /// The actor accepted the message but stopped before replying to it.
+    #[error("actor stopped before replying")]
+    Stopped,
+    /// The handler for the message panicked.
+    #[error("handler panicked")]
+    Panicked,
+    /// No response arrived before the timeout given to
+    /// [`Sender::ask_timeout`].
+    #[error("timed out waiting for a reply")]
+    Timeout,
+}
+
+/// Await the reply to an ask, recording a dead letter if it was dropped.
+async fn reply<A: Actor, M: Message>(
+    system: &SystemState,
+    rx: oneshot::Receiver<Result<M::Response, AskError>>,
+) -> Result<M::Response, AskError> {
+    rx.await.unwrap_or_else(|_| {
+        system.dead_letter(DeadLetter::new::<A, M>(DeadLetterReason::NoReply));
+        Err(AskError::Stopped)
+    })
+}
+
+/// Recover the payload of an envelope that could not be queued.
+fn undelivered<A: Actor, M: Message>(msg: PointerToActorMessage<A>) -> M {
+    msg.into_any()
+        .downcast::<Envelope<M>>()
+        .ok()
+        .and_then(|mut envelope| envelope.msg.take())
+        .expect("undelivered envelope holds its message")
+}
+
+/// Address of an actor. Messages are sent through this handle.
+///
+/// `Addr` is cheaply cloneable and can be used to:
+/// - Fire-and-forget a message via [`tell`](Sender::tell).
+/// - Await a response via [`ask`](Sender::ask).
+/// - Create a type-erased [`Recipient`] via [`recipient`](Sender::recipient).
+///
+/// Addresses compare, hash and print by the [`ActorId`] of their actor, so
+/// they can be kept in a `HashSet` of subscribers.
+pub struct Addr<A>
+where
+    A: Actor,
+{
+    id: ActorId,
+    path: Arc<str>,
+    tx: MailboxTx<A>,
+    stopped: CancellationToken,
+    stop_reason: Arc<Mutex<Option<StopReason>>>,
+    links: Arc<Links>,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<Metrics>,
+    system: Arc<SystemState>,
+}
+
+impl<A: Actor> Addr<A> {
+    /// Returns the identifier of the actor this address points to.
+    pub fn id(&self) -> ActorId {
+        self.id
+    }
+
+    /// Returns the actor's path, see [`Ctx::path`].
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns a snapshot of the actor's metrics.
+    ///
+    /// Requires the `metrics` feature.
+    ///
+    /// Counters accumulate across restarts and remain readable after the
+    /// actor has stopped.
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> ActorStats {
+        ActorStats {
+            id: self.id,
+            actor_type: std::any::type_name::<A>(),
+            mailbox_len: self.tx.len(),
+            processed: self.metrics.processed.load(Ordering::Relaxed),
+            panics: self.metrics.panics.load(Ordering::Relaxed),
+            restarts: self.metrics.restarts.load(Ordering::Relaxed),
+            handlers: self.metrics.handlers.lock().unwrap().clone(),
+        }
+    }
+
+    /// Wait until the actor task has fully stopped.
+    pub async fn wait_until_stopped(&self) {
+        self.stopped.cancelled().await;
+    }
+
+    /// Returns why the actor stopped, or `None` while it is still running.
+    ///
+    /// The reason is available as soon as
+    /// [`wait_until_stopped`](Addr::wait_until_stopped) returns.
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        if !self.stopped.is_cancelled() {
+            return None;
+        }
+        self.stop_reason.lock().unwrap().clone()
+    }
+
+    /// Returns `true` until the actor task has fully stopped.
+    pub fn is_alive(&self) -> bool {
+        !self.stopped.is_cancelled()
+    }
+
+    /// Returns `true` once the actor no longer accepts messages.
+    ///
+    /// The mailbox closes as soon as the actor begins stopping, which may be
+    /// some time before [`is_alive`](Addr::is_alive) turns `false` while
+    /// children are stopped and the [`stopped`](Actor::stopped) hook runs.
+    pub fn is_closed(&self) -> bool {
+        self.stopped.is_cancelled() || self.tx.is_closed()
+    }
+}
+
+impl<A> Clone for Addr<A>
+where
+    A: Actor,
+{
+    fn clone(&self) -> Self {
+        Addr {
+            id: self.id,
+            path: self.path.clone(),
+            tx: self.tx.clone(),
+            stopped: self.stopped.clone(),
+            stop_reason: self.stop_reason.clone(),
+            links: self.links.clone(),
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+            system: self.system.clone(),
+        }
+    }
+}
+
+impl<A: Actor> Addr<A> {
+    fn downgrade(&self) -> WeakAddr<A> {
+        WeakAddr {
+            id: self.id,
+            path: self.path.clone(),
+            tx: self.tx.downgrade(),
+            stopped: self.stopped.clone(),
+            stop_reason: self.stop_reason.clone(),
+            links: self.links.clone(),
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+            system: self.system.clone(),
+        }
+    }
+}
+
+/// An [`Addr`] that does not keep the actor's mailbox open, held by the
+/// actor's own [`Ctx`] and internal tasks so that the actor stops with
+/// [`StopReason::AddressesDropped`] once every user-held address is gone.
+struct WeakAddr<A: Actor> {
+    id: ActorId,
+    path: Arc<str>,
+    tx: WeakMailboxTx<A>,
+    stopped: CancellationToken,
+    stop_reason: Arc<Mutex<Option<StopReason>>>,
+    links: Arc<Links>,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<Metrics>,
+    system: Arc<SystemState>,
+}
+
+impl<A: Actor> Clone for WeakAddr<A> {
+    fn clone(&self) -> Self {
+        WeakAddr {
+            id: self.id,
+            path: self.path.clone(),
+            tx: self.tx.clone(),
+            stopped: self.stopped.clone(),
+            stop_reason: self.stop_reason.clone(),
+            links: self.links.clone(),
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+            system: self.system.clone(),
+        }
+    }
+}
+
+impl<A: Actor> WeakAddr<A> {
+    /// Returns a full address, unless every address has been dropped.
+    fn upgrade(&self) -> Option<Addr<A>> {
+        Some(self.with_tx(self.tx.upgrade()?))
+    }
+
+    /// Returns an address whose mailbox is already closed, for when every
+    /// address has been dropped.
+    fn closed(&self) -> Addr<A> {
+        self.with_tx(mailbox(Mailbox::Unbounded).0)
+    }
+
+    fn with_tx(&self, tx: MailboxTx<A>) -> Addr<A> {
+        Addr {
+            id: self.id,
+            path: self.path.clone(),
+            tx,
+            stopped: self.stopped.clone(),
+            stop_reason: self.stop_reason.clone(),
+            links: self.links.clone(),
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+            system: self.system.clone(),
+        }
+    }
+}
+
+/// Addresses are equal when they point to the same actor.
+impl<A: Actor> PartialEq for Addr<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<A: Actor> Eq for Addr<A> {}
+
+impl<A: Actor> Hash for Addr<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<A: Actor> fmt::Debug for Addr<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Addr")
+            .field("id", &self.id)
+            .field("path", &self.path)
+            .field("actor", &std::any::type_name::<A>())
+            .finish()
+    }
+}
+
+/// Capability to send messages of type `M` to an actor.
+///
+/// Implemented by both [`Addr`] and [`Recipient`].
+#[async_trait]
+pub trait Sender<M>
+where
+    M: Message,
+{
+    /// Send a message and await the response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the actor does not respond, see [`try_ask`](Sender::try_ask)
+    /// for the reasons this can happen.
+    async fn ask(&self, msg: M) -> M::Response;
+    /// Send a message and await the response, returning an [`AskError`]
+    /// instead of panicking if the actor does not respond.
+    async fn try_ask(&self, msg: M) -> Result<M::Response, AskError>;
+    /// Like [`try_ask`](Sender::try_ask) but gives up with
+    /// [`AskError::Timeout`] once `timeout` has elapsed.
+    ///
+    /// The deadline travels with the message: if the actor has not started
+    /// handling it by then, the message is skipped rather than processed for
+    /// nobody.
+    async fn ask_timeout(&self, msg: M, timeout: Duration) -> Result<M::Response, AskError>;
+    /// Send a message without waiting for a response (fire-and-forget).
+    ///
+    /// If the message cannot be queued, e.g. because a
+    /// [bounded](Mailbox::Bounded) mailbox is full, it is dropped and
+    /// published as a [`DeadLetter`]. Use [`send`](Sender::send) or
+    /// [`try_tell`](Sender::try_tell) when that matters.
+    fn tell(&self, msg: M);
+    /// Send a message without waiting for a response, first waiting for
+    /// mailbox capacity if the actor's mailbox is full.
+    async fn send(&self, msg: M) -> Result<(), SendError<M>>;
+    /// Send a message without waiting for a response or for mailbox
+    /// capacity.
+    ///
+    /// Returns [`SendError::Full`] if the actor's mailbox is at capacity and
+    /// [`SendError::Closed`] if the actor has stopped; either way the message
+    /// is handed back.
+    fn try_tell(&self, msg: M) -> Result<(), SendError<M>>;
+    /// Returns the identifier of the actor messages are sent to.
+    fn id(&self) -> ActorId;
+    /// Convert this sender into a type-erased [`Recipient`].
+    ///
+    /// This is useful for dependency injection: a `Recipient<M>` does not
+    /// expose the actor type.
+    fn recipient(self) -> Recipient<M>
+    where
+        Self: Sized + Send + Sync + 'static,
+    {
+        Recipient::new(Box::new(self))
+    }
+}
+
+#[async_trait]
+impl<M, A> Sender<M> for Addr<A>
+where
+    M: Message,
+    A: Actor + Handler<M>,
+{
+    async fn ask(&self, msg: M) -> M::Response {
+        self.try_ask(msg)
+            .await
+            .unwrap_or_else(|err| panic!("ask failed: {err}"))
+    }
+    async fn try_ask(&self, msg: M) -> Result<M::Response, AskError> {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(Envelope::new(Some(msg), Some(tx)))
+            .await
+            .map_err(|_| AskError::MailboxClosed)?;
+        reply::<A, M>(&self.system, rx).await
+    }
+    async fn ask_timeout(&self, msg: M, timeout: Duration) -> Result<M::Response, AskError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let (tx, rx) = oneshot::channel();
+        let envelope = Envelope::new(Some(msg), Some(tx)).with_deadline(deadline);
+        tokio::time::timeout_at(deadline, async {
+            self.tx
+                .send(envelope)
+                .await
+                .map_err(|_| AskError::MailboxClosed)?;
+            reply::<A, M>(&self.system, rx).await
+        })
+        .await
+        .unwrap_or(Err(AskError::Timeout))
+    }
+    fn tell(&self, msg: M) {
+        if let Err(err) = self.tx.try_send(Envelope::new(Some(msg), None)) {
+            let reason = match err {
+                SendError::Full(_) => DeadLetterReason::MailboxFull,
+                SendError::Closed(_) => DeadLetterReason::Stopped,
+            };
+            self.system.dead_letter(DeadLetter::new::<A, M>(reason));
+        }
+    }
+    async fn send(&self, msg: M) -> Result<(), SendError<M>> {
+        self.tx
+            .send(Envelope::new(Some(msg), None))
+            .await
+            .map_err(|e| e.map(undelivered::<A, M>))
+    }
+    fn try_tell(&self, msg: M) -> Result<(), SendError<M>> {
+        self.tx
+            .try_send(Envelope::new(Some(msg), None))
+            .map_err(|e| e.map(undelivered::<A, M>))
+    }
+    fn id(&self) -> ActorId {
+        self.id
+    }
+}
+
+/// Type-erased sender for a specific message type.
+///
+/// Wraps any [`Sender<M>`] behind a trait object so the concrete actor type
+/// is hidden. Useful for dependency injection where you want to expose only
+/// the ability to send a particular message.
+///
+/// Recipients compare, hash and print by the [`ActorId`] of their actor, so a
+/// recipient equals any other recipient for the same actor.
+pub struct Recipient<M: Message> {
+    id: ActorId,
+    tx: Box<dyn Sender<M> + Send + Sync + 'static>,
+}
+
+impl<M> Recipient<M>
+where
+    M: Message,
+{
+    /// Create a new `Recipient` from a boxed [`Sender`].
+    ///
+    /// The recipient takes its [`ActorId`] from the sender.
+    pub fn new(tx: Box<dyn Sender<M> + Send + Sync + 'static>) -> Self {
+        Recipient { id: tx.id(), tx }
+    }
+
+    /// Returns the identifier of the actor messages are sent to.
+    pub fn id(&self) -> ActorId {
+        self.id
+    }
+}
+
+impl<M: Message> PartialEq for Recipient<M> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<M: Message> Eq for Recipient<M> {}
+
+impl<M: Message> Hash for Recipient<M> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl<M: Message> fmt::Debug for Recipient<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recipient")
+            .field("id", &self.id)
+            .field("message", &std::any::type_name::<M>())
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<M> Sender<M> for Recipient<M>
+where
+    M: Message,
+{
+    async fn ask(&self, msg: M) -> M::Response {
+        self.tx.ask(msg).await
+    }
+
+    async fn try_ask(&self, msg: M) -> Result<M::Response, AskError> {
+        self.tx.try_ask(msg).await
+    }
+
+    async fn ask_timeout(&self, msg: M, timeout: Duration) -> Result<M::Response, AskError> {
+        self.tx.ask_timeout(msg, timeout).await
+    }
+
+    fn tell(&self, msg: M) {
+        self.tx.tell(msg);
+    }
+
+    async fn send(&self, msg: M) -> Result<(), SendError<M>> {
+        self.tx.send(msg).await
+    }
+
+    fn try_tell(&self, msg: M) -> Result<(), SendError<M>> {
+        self.tx.try_tell(msg)
+    }
+
+    fn id(&self) -> ActorId {
+        self.id
+    }
+
+    fn recipient(self) -> Recipient<M> {
+        self
+    }
+}
+
+/// The messages an actor can be sent through [`ActorSelection::recipients`],
+/// declared in [`Actor::expose`].
+pub struct Exposed<A: Actor> {
+    recipients: HashMap<TypeId, MakeRecipient<A>>,
+}
+
+/// Upgrades an actor's address to a `Recipient<M>`, boxed as `Any`.
+type MakeRecipient<A> = Box<dyn Fn(&WeakAddr<A>) -> Option<Box<dyn Any>> + Send + Sync>;
+
+impl<A: Actor> Exposed<A> {
+    fn new() -> Self {
+        let mut exposed = Self {
+            recipients: HashMap::new(),
+        };
+        A::expose(&mut exposed);
+        exposed
+    }
+
+    /// Let selections reach the actor as a `Recipient<M>`.
+    pub fn message<M: Message>(&mut self) -> &mut Self
+    where
+        A: Handler<M>,
+    {
+        self.recipients.insert(
+            TypeId::of::<M>(),
+            Box::new(|addr| {
+                let recipient = Sender::<M>::recipient(addr.upgrade()?);
+                Some(Box::new(recipient))
+            }),
+        );
+        self
+    }
+}
+
+/// The running actors whose [paths](Ctx::path) match a pattern, created with
+/// [`Ctx::select`].
+///
+/// Patterns are matched segment by segment: `*` matches any one segment and
+/// `**` any number of segments, including none. So `/system/orders/*`
+/// selects the children of `orders`, and `/system/orders/**` the whole
+/// subtree including `orders` itself. The system's root actor is never
+/// selected.
+///
+/// A selection is resolved afresh each time it is used, so it picks up
+/// actors spawned after it was created.
+///
+/// ```rust
+/// use tactix::{Actor, ActorSystem, Ctx, Exposed, Handler, Message, Sender};
+///
+/// struct Worker;
+///
+/// impl Actor for Worker {
+///     fn expose(exposed: &mut Exposed<Self>) {
+///         exposed.message::<Flush>();
+///     }
+/// }
+///
+/// #[derive(Message, Clone)]
+/// struct Flush;
+///
+/// impl Handler<Flush> for Worker {
+///     async fn handle(&mut self, _: Flush, _: &Ctx<Self>) {}
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let system = ActorSystem::new(Default::default());
+/// system.spawn_named("worker-1", || Worker).unwrap();
+/// system.spawn_named("worker-2", || Worker).unwrap();
+///
+/// let workers = system.select("/system/*").recipients::<Flush>();
+/// assert_eq!(workers.len(), 2);
+/// for worker in &workers {
+///     worker.tell(Flush);
+/// }
+/// # system.shutdown().await;
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ActorSelection {
+    pattern: String,
+    root: Weak<Children>,
+}
+
+impl ActorSelection {
+    /// Returns the absolute pattern this selection matches.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Addresses of the selected actors of type `A`, in spawn order with
+    /// parents before their children.
+    ///
+    /// Selected actors of any other type are skipped.
+    pub fn addrs<A: Actor>(&self) -> Vec<Addr<A>> {
+        let mut found = Vec::new();
+        self.resolve(&mut |actor| {
+            if let Some(addr) = actor
+                .addr()
+                .downcast_ref::<WeakAddr<A>>()
+                .and_then(WeakAddr::upgrade)
+            {
+                found.push(addr);
+            }
+        });
+        found
+    }
+
+    /// Recipients for the selected actors that [expose](Actor::expose) `M`,
+    /// whatever their type, in the same order as
+    /// [`addrs`](ActorSelection::addrs).
+    ///
+    /// Selected actors that do not expose `M` are skipped, even if they
+    /// handle it.
+    pub fn recipients<M: Message>(&self) -> Vec<Recipient<M>> {
+        let mut found = Vec::new();
+        self.resolve(&mut |actor| {
+            if let Some(recipient) = actor
+                .recipient(TypeId::of::<M>())
+                .and_then(|recipient| recipient.downcast::<Recipient<M>>().ok())
+            {
+                found.push(*recipient);
+            }
+        });
+        found
+    }
+
+    /// Call `found` with every running actor whose path matches.
+    fn resolve(&self, found: &mut dyn FnMut(&dyn Supervised)) {
+        if let Some(root) = self.root.upgrade() {
+            let pattern: Vec<_> = segments(&self.pattern).collect();
+            select_in(&root.lock().unwrap().clone(), &pattern, found);
+        }
+    }
+}
+
+impl fmt::Debug for ActorSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActorSelection")
+            .field("pattern", &self.pattern)
+            .finish()
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+/// Call `found` with the running actors in `actors` and their descendants
+/// whose paths match `pattern`.
+fn select_in(
+    actors: &[Arc<dyn Supervised>],
+    pattern: &[&str],
+    found: &mut dyn FnMut(&dyn Supervised),
+) {
+    for actor in actors {
+        if !actor.is_alive() {
+            continue;
+        }
+        let path: Vec<_> = segments(actor.path()).collect();
+        if path_matches(pattern, &path) {
+            found(actor.as_ref());
+        }
+        select_in(&actor.children(), pattern, found);
+    }
+}
+
+fn path_matches(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (Some((&"**", rest)), _) => {
+            path_matches(rest, path) || (!path.is_empty() && path_matches(pattern, &path[1..]))
+        }
+        (Some((expected, pattern)), Some((segment, path))) => {
+            (*expected == "*" || expected == segment) && path_matches(pattern, path)
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+#[async_trait]
+impl<A> Stoppable for Ctx<A>
+where
+    A: Actor,
+{
+    fn stop(&self) {
+        self.stop_with(StopReason::Stopped);
+    }
+
+    async fn wait_until_stopped(&self) {
+        self.stopped.cancelled().await;
+    }
+
+    async fn stop_all_children(&self) {
+        // Children stay listed until they have stopped, so that aborting this
+        // actor midway still reaches them.
+        let children: Vec<_> = self.children.lock().unwrap().clone();
+        future::join_all(children.iter().map(|child| async move {
+            child.stop_with(StopReason::ParentStopped);
+            match child.stop_timeout() {
+                Some(timeout) => {
+                    if tokio::time::timeout(timeout, child.wait_until_stopped())
+                        .await
+                        .is_err()
+                    {
+                        child.abort(timeout);
+                    }
+                }
+                None => child.wait_until_stopped().await,
+            }
+        }))
+        .await;
+        let stopped: HashSet<_> = children.iter().map(|child| child.id()).collect();
+        self.children
+            .lock()
+            .unwrap()
+            .retain(|child| !stopped.contains(&child.id()));
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::let_and_return)]
+mod simple_tests {
+    use crate::{Actor, Addr, Ctx, Handler, Message, Sender, StopReason, Stoppable};
+    use std::time::Instant;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn simple_counter() -> anyhow::Result<()> {
+        // Simple counter
+        struct Counter {
+            count: i64,
+        }
+
+        impl Actor for Counter {}
+
+        #[derive(Message)]
+        struct Increment;
+
+        #[derive(Message)]
+        struct Decrement;
+
+        #[derive(Message)]
+        #[response(i64)]
+        struct GetCount;
+
+        impl Handler<Increment> for Counter {
+            async fn handle(&mut self, _: Increment, _: &Ctx<Self>) {
+                self.count += 1;
+            }
+        }
+
+        impl Handler<Decrement> for Counter {
+            async fn handle(&mut self, _: Decrement, _: &Ctx<Self>) {
+                self.count -= 1;
+            }
+        }
+
+        impl Handler<GetCount> for Counter {
+            async fn handle(&mut self, _: GetCount, _: &Ctx<Self>) -> i64 {
+                self.count
+            }
+        }
+
+        let counter = Counter { count: 0 }.start();
+
+        let mut handles = vec![];
+
+        let t1 = counter.clone();
+        let t2 = counter.clone();
+
+        let total = 10_000_000;
+        let start = Instant::now();
+        handles.push(tokio::task::spawn(async move {
+            for _ in 0..(total / 2) {
+                t1.tell(Increment);
+            }
+            Ok::<_, anyhow::Error>(())
+        }));
+        handles.push(tokio::task::spawn(async move {
+            for _ in 0..(total / 2) {
+                t2.tell(Decrement);
+            }
+            Ok::<_, anyhow::Error>(())
+        }));
+        for handle in handles {
+            handle.await??;
+        }
+        let count = counter.ask(GetCount).await;
+        assert_eq!(count, 0);
+        let finished = start.elapsed();
+        let msg_per_sec = total as f64 / finished.as_secs_f64();
+        println!("{:.1} million msg/sec", msg_per_sec / 1_000_000.0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restart_counter() -> anyhow::Result<()> {
+        struct Db {
+            value: i64,
+        }
+
+        impl Actor for Db {
+            async fn stopped(&mut self, _: &StopReason, _: &Ctx<Self>) {
+                println!("Db stopped");
+            }
+        }
+
+        #[derive(Message)]
+        #[response(i64)]
+        struct DbGet;
+
+        #[derive(Message)]
+        struct DbSet(i64);
+
+        impl Handler<DbGet> for Db {
+            async fn handle(&mut self, _: DbGet, _: &Ctx<Self>) -> i64 {
+                self.value
+            }
+        }
+
+        impl Handler<DbSet> for Db {
+            async fn handle(&mut self, msg: DbSet, _: &Ctx<Self>) {
+                self.value = msg.0;
+            }
+        }
+
+        struct Counter {
+            db: Addr<Db>,
+        }
+
+        impl Actor for Counter {
+            async fn stopped(&mut self, _: &StopReason, _: &Ctx<Self>) {
+                println!("Counter stopped");
+            }
+        }
+
+        #[derive(Message)]
+        #[response(i64)]
+        struct Increment;
+
+        #[derive(Message)]
+        #[response(i64)]
+        struct GetCount;
+
+        #[derive(Message)]
+        struct Poison;
+
+        #[derive(Message)]
+        struct Stop;
+
+        impl Handler<Increment> for Counter {
+            async fn handle(&mut self, _: Increment, _: &Ctx<Self>) -> i64 {
+                let count = self.db.ask(DbGet).await + 1;
+                self.db.tell(DbSet(count));
+                count
+            }
+        }
+
+        impl Handler<GetCount> for Counter {
+            async fn handle(&mut self, _: GetCount, _: &Ctx<Self>) -> i64 {
+                self.db.ask(DbGet).await
+            }
+        }
+
+        impl Handler<Poison> for Counter {
+            async fn handle(&mut self, _: Poison, _: &Ctx<Self>) {
+                panic!("poisoned!");
+            }
+        }
+
+        struct Root {}
+
+        impl Actor for Root {
+            async fn stopped(&mut self, _: &StopReason, _: &Ctx<Self>) {
+                println!("Root stopped");
+            }
+        }
+
+        impl Handler<Stop> for Root {
+            async fn handle(&mut self, _: Stop, ctx: &Ctx<Self>) {
+                println!("in Stop handler");
+                ctx.stop();
+            }
+        }
+
+        #[derive(Message)]
+        #[response(Addr<Counter>)]
+        struct GetCounter;
+
+        impl Handler<GetCounter> for Root {
+            async fn handle(&mut self, _: GetCounter, ctx: &Ctx<Self>) -> Addr<Counter> {
+                let db = ctx.spawn(|| Db { value: 0 });
+                let counter = ctx.spawn(move || Counter { db: db.clone() });
+                counter
+            }
+        }
+
+        let root = Root {}.start();
+
+        let counter = root.ask(GetCounter).await;
+        for _ in 0..5 {
+            counter.tell(Increment);
+        }
+        assert_eq!(counter.ask(GetCount).await, 5);
+
+        counter.tell(Poison);
+        counter.ask(Increment).await;
+
+        let count = counter.ask(GetCount).await;
+        assert_eq!(count, 6, "state survives because it lives in the db actor");
+        root.tell(Stop);
+        println!("just called stop!");
+        root.wait_until_stopped().await;
+        println!("goodbye");
+        Ok(())
+    }
+}
+
+//////////////////////////
+
+#[cfg(test)]
+mod bank_tests {
+    use std::time::Duration;
+
+    use crate::{Actor, Ctx, Handler, Message, Sender};
+    use tokio::time::sleep;
+
+    struct Deposit(u64);
+    impl Message for Deposit {
+        type Response = ();
+    }
+
+    struct Withdraw(u64);
+    impl Message for Withdraw {
+        type Response = Result<(), String>;
+    }
+
+    struct GetBalance;
+    impl Message for GetBalance {
+        type Response = u64;
+    }
+
+    struct GetAccountInfo;
+    impl Message for GetAccountInfo {
+        type Response = (u64, u64, u64);
+    }
+
+    #[derive(Clone)]
+    struct BankAccount {
+        balance: u64,
+        total_deposits: u64,
+        total_withdrawals: u64,
+    }
+
+    impl BankAccount {
+        fn new(initial_balance: u64) -> Self {
+            Self {
+                balance: initial_balance,
+                total_deposits: 0,
+                total_withdrawals: 0,
+            }
+        }
+    }
+
+    impl Actor for BankAccount {}
+
+    impl Handler<Deposit> for BankAccount {
+        async fn handle(&mut self, msg: Deposit, _: &Ctx<Self>) {
+            tokio::time::sleep(Duration::from_millis(6)).await;
+            self.balance += msg.0;
+            self.total_deposits += msg.0;
+            println!("Deposit: {}. New balance: {}", msg.0, self.balance);
+        }
+    }
+    impl Handler<Withdraw> for BankAccount {
+        async fn handle(&mut self, msg: Withdraw, _: &Ctx<Self>) -> Result<(), String> {
+            if self.balance >= msg.0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                self.balance -= msg.0;
+                self.total_withdrawals += msg.0;
+                println!("Withdrawal: {}. New balance: {}", msg.0, self.balance);
+                Ok(())
+            } else {
+                Err(format!(
+                    "Insufficient funds. Current balance: {}",
+                    self.balance
+                ))
+            }
+        }
+    }
+
+    impl Handler<GetAccountInfo> for BankAccount {
+        async fn handle(&mut self, _msg: GetAccountInfo, _: &Ctx<Self>) -> (u64, u64, u64) {
+            println!("GetAccountInfo!");
+            (self.balance, self.total_deposits, self.total_withdrawals)
+        }
+    }
+    impl Handler<GetBalance> for BankAccount {
+        async fn handle(&mut self, _msg: GetBalance, _: &Ctx<Self>) -> u64 {
+            self.balance
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bank_account_race_condition() {
+        let initial_balance = 1000;
+        let account = BankAccount::new(initial_balance).start();
+
+        let deposit_amount = 100;
+        let withdraw_amount = 200;
+        let num_operations = 5;
+
+        // Spawn multiple tasks to deposit and withdraw concurrently
+        let deposit_task = tokio::spawn({
+            let account = account.clone();
+            async move {
+                for _ in 0..num_operations {
+                    account.tell(Deposit(deposit_amount));
+                    sleep(Duration::from_millis(3)).await;
+                }
+            }
+        });
+
+        let withdraw_task = tokio::spawn({
+            let account = account.clone();
+            async move {
+                for _ in 0..num_operations {
+                    account.tell(Withdraw(withdraw_amount));
+                    sleep(Duration::from_millis(9)).await;
+                }
+            }
+        });
+
+        // Wait for both tasks to complete
+        let _ = tokio::join!(deposit_task, withdraw_task);
+
+        // Get the final account info
+        let (final_balance, total_deposits, total_withdrawals) = account.ask(GetAccountInfo).await;
+
+        // Assertions to check for race conditions
+        let expected_deposits = deposit_amount * num_operations;
+        let expected_withdrawals = withdraw_amount * num_operations;
+        let expected_balance = initial_balance + expected_deposits - expected_withdrawals;
+
+        assert_eq!(
+            total_deposits, expected_deposits,
+            "Total deposits don't match expected value"
+        );
+        assert_eq!(
+            total_withdrawals, expected_withdrawals,
+            "Total withdrawals don't match expected value"
+        );
+
+        // This assertion might fail due to race condition
+        assert_eq!(
+            final_balance, expected_balance,
+            "Final balance doesn't match expected value"
+        );
+
+        // This assertion checks if the balance is consistent with deposits and withdrawals
+        assert_eq!(
+            final_balance,
+            initial_balance + total_deposits - total_withdrawals,
+            "Balance is inconsistent with recorded deposits and withdrawals"
+        );
+    }
+}
+
+#[cfg(test)]
+mod mailbox_tests {
+    use std::num::NonZeroUsize;
+    use std::sync::Arc;
+
+    use crate::{
+        Actor, ActorConfig, ActorSystem, Ctx, Handler, Mailbox, Message, SendError, Sender,
+        SupervisionStrategy,
+    };
+    use tokio::sync::Semaphore;
+
+    struct Gated {
+        gate: Arc<Semaphore>,
+        seen: u64,
+    }
+
+    impl Actor for Gated {}
+
+    #[derive(Message)]
+    struct Work(u64);
+
+    #[derive(Message)]
+    #[response(u64)]
+    struct Seen;
+
+    impl Handler<Work> for Gated {
+        async fn handle(&mut self, msg: Work, _: &Ctx<Self>) {
+            self.gate.acquire().await.unwrap().forget();
+            self.seen += msg.0;
+        }
+    }
+
+    impl Handler<Seen> for Gated {
+        async fn handle(&mut self, _: Seen, _: &Ctx<Self>) -> u64 {
+            self.seen
+        }
+    }
+
+    #[tokio::test]
+    async fn bounded_mailbox_applies_backpressure() {
+        let gate = Arc::new(Semaphore::new(0));
+        let actor = ActorSystem::global().spawn_with_config(
+            {
+                let gate = gate.clone();
+                move || Gated {
+                    gate: gate.clone(),
+                    seen: 0,
+                }
+            },
+            ActorConfig {
+                supervision: Some(SupervisionStrategy::NoRestart),
+                mailbox: Some(Mailbox::Bounded(NonZeroUsize::MIN)),
+                ..Default::default()
+            },
+        );
+
+        // The first message is picked up by the handler, which blocks on the
+        // gate; the second fills the single mailbox slot.
+        actor.send(Work(1)).await.unwrap();
+        tokio::task::yield_now().await;
+        while actor.try_tell(Work(2)).is_err() {
+            tokio::task::yield_now().await;
+        }
+
+        match actor.try_tell(Work(4)) {
+            Err(SendError::Full(Work(n))) => assert_eq!(n, 4),
+            other => panic!("expected a full mailbox, got {other:?}"),
+        }
+
+        let pending = tokio::spawn({
+            let actor = actor.clone();
+            async move { actor.send(Work(8)).await }
+        });
+        gate.add_permits(3);
+        pending.await.unwrap().unwrap();
+
+        assert_eq!(actor.ask(Seen).await, 11);
+    }
+}
+
+#[cfg(test)]
+mod ask_tests {
+    use std::time::Duration;
+
+    use crate::{Actor, AskError, Ctx, Handler, Message, SendError, Sender, Stoppable};
+
+    struct Fragile;
+
+    impl Actor for Fragile {}
+
+    #[derive(Message)]
+    #[response(u8)]
+    struct Ping;
+
+    #[derive(Message)]
+    #[response(u8)]
+    struct Explode;
+
+    #[derive(Message)]
+    struct StopSelf;
+
+    impl Handler<Ping> for Fragile {
+        async fn handle(&mut self, _: Ping, _: &Ctx<Self>) -> u8 {
+            1
+        }
+    }
+
+    impl Handler<Explode> for Fragile {
+        async fn handle(&mut self, _: Explode, _: &Ctx<Self>) -> u8 {
+            panic!("boom");
+        }
+    }
+
+    impl Handler<StopSelf> for Fragile {
+        async fn handle(&mut self, _: StopSelf, ctx: &Ctx<Self>) {
+            ctx.stop();
+        }
+    }
+
+    #[tokio::test]
+    async fn try_ask_reports_panics() {
+        let actor = Fragile.start();
+        assert_eq!(actor.try_ask(Ping).await, Ok(1));
+        assert_eq!(actor.try_ask(Explode).await, Err(AskError::Panicked));
+    }
+
+    #[tokio::test]
+    async fn try_ask_reports_stopped_actors() {
+        let actor = Fragile.start();
+        actor.tell(StopSelf);
+        assert_eq!(actor.try_ask(Ping).await, Err(AskError::Stopped));
+
+        actor.wait_until_stopped().await;
+        assert_eq!(actor.try_ask(Ping).await, Err(AskError::MailboxClosed));
+    }
+
+    #[tokio::test]
+    async fn try_tell_returns_messages_to_stopped_actors() {
+        let actor = Fragile.start();
+        assert!(actor.is_alive());
+        assert!(!actor.is_closed());
+
+        actor.tell(StopSelf);
+        actor.wait_until_stopped().await;
+        assert!(!actor.is_alive());
+        assert!(actor.is_closed());
+        assert!(matches!(
+            actor.try_tell(StopSelf),
+            Err(SendError::Closed(StopSelf))
+        ));
+    }
+
+    struct Slow {
+        handled: u64,
+    }
+
+    impl Actor for Slow {}
+
+    #[derive(Message)]
+    #[response(u64)]
+    struct Nap(u64);
+
+    impl Handler<Nap> for Slow {
+        async fn handle(&mut self, msg: Nap, _: &Ctx<Self>) -> u64 {
+            tokio::time::sleep(Duration::from_millis(msg.0)).await;
+            self.handled += 1;
+            self.handled
+        }
+    }
+
+    #[tokio::test]
+    async fn ask_timeout_skips_expired_messages() {
+        let actor = Slow { handled: 0 }.start();
+        let r = actor.clone().recipient();
+
+        actor.tell(Nap(100));
+        assert_eq!(
+            r.ask_timeout(Nap(0), Duration::from_millis(10)).await,
+            Err(AskError::Timeout)
+        );
+        // The first nap is handled; the timed out one is skipped.
+        assert_eq!(
+            actor.ask_timeout(Nap(0), Duration::from_secs(5)).await,
+            Ok(2)
+        );
+    }
+}
+
+#[cfg(test)]
+mod dead_letter_tests {
+    use std::time::Duration;
+
+    use crate::{
+        Actor, ActorSystem, Ctx, DeadLetter, DeadLetterReason, Handler, Message, Sender, Stoppable,
+    };
+    use tokio::sync::broadcast;
+
+    struct Quitter;
+
+    impl Actor for Quitter {}
+
+    #[derive(Message)]
+    struct Quit;
+
+    #[derive(Message)]
+    struct Note;
+
+    impl Handler<Quit> for Quitter {
+        async fn handle(&mut self, _: Quit, ctx: &Ctx<Self>) {
+            ctx.stop();
+        }
+    }
+
+    impl Handler<Note> for Quitter {
+        async fn handle(&mut self, _: Note, _: &Ctx<Self>) {}
+    }
+
+    async fn next_letter(rx: &mut broadcast::Receiver<DeadLetter>) -> DeadLetter {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let letter = rx.recv().await.unwrap();
+                if letter.actor == std::any::type_name::<Quitter>() {
+                    return letter;
+                }
+            }
+        })
+        .await
+        .expect("no dead letter published")
+    }
+
+    #[tokio::test]
+    async fn undelivered_messages_become_dead_letters() {
+        let mut letters = ActorSystem::dead_letters();
+        let actor = Quitter.start();
+
+        // Queued behind `Quit`, so never handled.
+        actor.tell(Quit);
+        actor.tell(Note);
+        let letter = next_letter(&mut letters).await;
+        assert_eq!(letter.message, std::any::type_name::<Note>());
+        assert_eq!(letter.reason, DeadLetterReason::Unprocessed);
+
+        // Sent after the actor stopped.
+        actor.wait_until_stopped().await;
+        actor.tell(Note);
+        let letter = next_letter(&mut letters).await;
+        assert_eq!(letter.reason, DeadLetterReason::Stopped);
+    }
+}
+
+#[cfg(test)]
+mod child_strategy_tests {
+    use std::time::Duration;
+
+    use crate::{
+        Actor, ActorConfig, ActorSystem, Addr, Backoff, ChildStrategy, Ctx, Handler, Message,
+        Sender, SupervisionStrategy,
+    };
+
+    struct Worker {
+        count: u64,
+    }
+
+    impl Actor for Worker {}
+
+    #[derive(Message)]
+    struct Bump;
+
+    #[derive(Message)]
+    struct Crash;
+
+    #[derive(Message)]
+    #[response(u64)]
+    struct Count;
+
+    impl Handler<Bump> for Worker {
+        async fn handle(&mut self, _: Bump, _: &Ctx<Self>) {
+            self.count += 1;
+        }
+    }
+
+    impl Handler<Crash> for Worker {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
+        }
+    }
+
+    impl Handler<Count> for Worker {
+        async fn handle(&mut self, _: Count, _: &Ctx<Self>) -> u64 {
+            self.count
+        }
+    }
+
+    struct Parent;
+
+    impl Actor for Parent {}
+
+    #[derive(Message)]
+    #[response(Vec<Addr<Worker>>)]
+    struct SpawnWorkers;
+
+    impl Handler<SpawnWorkers> for Parent {
+        async fn handle(&mut self, _: SpawnWorkers, ctx: &Ctx<Self>) -> Vec<Addr<Worker>> {
+            (0..3)
+                .map(|_| {
+                    ctx.spawn_with_config(
+                        || Worker { count: 0 },
+                        SupervisionStrategy::Restart {
+                            window: Duration::from_secs(5),
+                            max_restarts: 0,
+                            backoff: None,
+                        },
+                    )
+                })
+                .collect()
+        }
+    }
+
+    /// Bump every worker, crash the middle one and report each worker's count
+    /// once the parent has applied `strategy`.
+    async fn counts_after_crash(strategy: ChildStrategy) -> Vec<u64> {
+        let parent = ActorSystem::global().spawn_with_config(
+            || Parent,
+            ActorConfig {
+                child_strategy: strategy,
+                ..SupervisionStrategy::NoRestart.into()
+            },
+        );
+        let workers = parent.ask(SpawnWorkers).await;
+        for worker in &workers {
+            worker.tell(Bump);
+        }
+        workers[1].tell(Crash);
+        // Answered only once the parent has restarted the failed worker.
+        workers[1].ask(Count).await;
+        let mut counts = Vec::new();
+        for worker in &workers {
+            counts.push(worker.ask(Count).await);
+        }
+        counts
+    }
+
+    #[tokio::test]
+    async fn one_for_one_restarts_only_the_failed_child() {
+        assert_eq!(
+            counts_after_crash(ChildStrategy::OneForOne).await,
+            [1, 0, 1]
+        );
+    }
+
+    #[tokio::test]
+    async fn one_for_all_restarts_every_child() {
+        assert_eq!(
+            counts_after_crash(ChildStrategy::OneForAll).await,
+            [0, 0, 0]
+        );
+    }
+
+    #[tokio::test]
+    async fn rest_for_one_restarts_later_children() {
+        assert_eq!(
+            counts_after_crash(ChildStrategy::RestForOne).await,
+            [1, 0, 0]
+        );
+    }
+
+    /// Restarts after a backoff and reports how often it was restarted.
+    struct Patient {
+        restarts: u64,
+    }
+
+    impl Actor for Patient {
+        async fn restarted(&mut self, restarts: u64, _: &Ctx<Self>) {
+            self.restarts = restarts;
+        }
+    }
+
+    impl Handler<Crash> for Patient {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
+        }
+    }
+
+    impl Handler<Count> for Patient {
+        async fn handle(&mut self, _: Count, _: &Ctx<Self>) -> u64 {
+            self.restarts
+        }
+    }
+
+    #[derive(Message)]
+    #[response((Addr<Patient>, Addr<Worker>))]
+    struct SpawnPair;
+
+    impl Handler<SpawnPair> for Parent {
+        async fn handle(&mut self, _: SpawnPair, ctx: &Ctx<Self>) -> (Addr<Patient>, Addr<Worker>) {
+            let patient = ctx.spawn_with_config(
+                || Patient { restarts: 0 },
+                SupervisionStrategy::Restart {
+                    window: Duration::from_secs(5),
+                    max_restarts: 5,
+                    backoff: Some(Backoff {
+                        min: Duration::from_millis(100),
+                        ..Default::default()
+                    }),
+                },
+            );
+            let worker = ctx.spawn_with_config(
+                || Worker { count: 0 },
+                SupervisionStrategy::Restart {
+                    window: Duration::from_secs(5),
+                    max_restarts: 0,
+                    backoff: None,
+                },
+            );
+            (patient, worker)
+        }
+    }
+
+    #[tokio::test]
+    async fn siblings_in_backoff_restart_once() {
+        let parent = ActorSystem::global().spawn_with_config(
+            || Parent,
+            ActorConfig {
+                child_strategy: ChildStrategy::OneForAll,
+                ..SupervisionStrategy::NoRestart.into()
+            },
+        );
+        let (patient, worker) = parent.ask(SpawnPair).await;
+        patient.tell(Crash);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // Escalates while the patient is still backing off.
+        worker.tell(Crash);
+        worker.ask(Count).await;
+        assert_eq!(patient.ask(Count).await, 1);
+    }
+}
+
+#[cfg(test)]
+mod backoff_tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{Actor, ActorSystem, Backoff, Ctx, Handler, Message, Sender, SupervisionStrategy};
+
+    #[test]
+    fn delay_grows_exponentially_up_to_max() {
+        let backoff = Backoff {
+            min: Duration::from_millis(10),
+            max: Duration::from_millis(50),
+            multiplier: 2.0,
+            jitter: None,
+        };
+        let delays: Vec<_> = (1..=4).map(|n| backoff.delay(n).as_millis()).collect();
+        assert_eq!(delays, [10, 20, 40, 50]);
+        assert_eq!(backoff.delay(u64::MAX), Duration::from_millis(50));
+
+        let jittered = Backoff {
+            jitter: Some(0.5),
+            ..backoff
+        };
+        for _ in 0..100 {
+            let delay = jittered.delay(2);
+            assert!(delay > Duration::from_millis(10) && delay <= Duration::from_millis(20));
+        }
+    }
+
+    struct Flaky;
+
+    impl Actor for Flaky {}
+
+    #[derive(Message)]
+    struct Fail;
+
+    #[derive(Message)]
+    struct Ping;
+
+    impl Handler<Fail> for Flaky {
+        async fn handle(&mut self, _: Fail, _: &Ctx<Self>) {
+            panic!("fail");
+        }
+    }
+
+    impl Handler<Ping> for Flaky {
+        async fn handle(&mut self, _: Ping, _: &Ctx<Self>) {}
+    }
+
+    #[tokio::test]
+    async fn restarts_wait_for_the_backoff() {
+        let actor = ActorSystem::global().spawn_with_config(
+            || Flaky,
+            SupervisionStrategy::Restart {
+                window: Duration::from_secs(5),
+                max_restarts: 5,
+                backoff: Some(Backoff {
+                    min: Duration::from_millis(50),
+                    ..Default::default()
+                }),
+            },
+        );
+        let start = Instant::now();
+        actor.tell(Fail);
+        actor.ask(Ping).await;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
+
+#[cfg(test)]
+mod restart_window_tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::{
+        Actor, ActorSystem, Addr, AskError, ChildFailure, Ctx, Handler, Interrupt, Message, Sender,
+        SupervisionStrategy,
+    };
+
+    struct Flaky;
+
+    impl Actor for Flaky {}
+
+    #[derive(Message)]
+    struct Fail;
+
+    #[derive(Message)]
+    struct Ping;
+
+    impl Handler<Fail> for Flaky {
+        async fn handle(&mut self, _: Fail, _: &Ctx<Self>) {
+            panic!("fail");
+        }
+    }
+
+    impl Handler<Ping> for Flaky {
+        async fn handle(&mut self, _: Ping, _: &Ctx<Self>) {}
+    }
+
+    struct Parent {
+        failed: Arc<Mutex<Vec<ChildFailure>>>,
+    }
+
+    impl Actor for Parent {
+        async fn child_escalated(
+            &mut self,
+            failure: &ChildFailure,
+            _: &Ctx<Self>,
+        ) -> Option<Interrupt> {
+            self.failed.lock().unwrap().push(failure.clone());
+            None
+        }
+    }
+
+    #[derive(Message)]
+    #[response(Addr<Flaky>)]
+    struct SpawnChild;
+
+    impl Handler<SpawnChild> for Parent {
+        async fn handle(&mut self, _: SpawnChild, ctx: &Ctx<Self>) -> Addr<Flaky> {
+            ctx.spawn_with_config(
+                || Flaky,
+                SupervisionStrategy::Restart {
+                    window: Duration::from_millis(300),
+                    max_restarts: 2,
+                    backoff: None,
+                },
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn budget_applies_to_a_sliding_window() {
+        let failed = Arc::new(Mutex::new(Vec::new()));
+        let parent = ActorSystem::global().spawn_with_config(
+            {
+                let failed = failed.clone();
+                move || Parent {
+                    failed: failed.clone(),
+                }
+            },
+            SupervisionStrategy::NoRestart,
+        );
+        let child = parent.ask(SpawnChild).await;
+
+        child.tell(Fail);
+        child.tell(Fail);
+        child.ask(Ping).await;
+
+        // Once the earlier restarts slide out of the window the budget is
+        // available again.
+        tokio::time::sleep(Duration::from_millis(350)).await;
+        child.tell(Fail);
+        child.tell(Fail);
+        child.ask(Ping).await;
+        assert!(failed.lock().unwrap().is_empty());
+
+        child.tell(Fail);
+        assert_eq!(child.try_ask(Ping).await, Err(AskError::Stopped));
+        let failed = failed.lock().unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].id, child.id());
+        assert_eq!(failed[0].actor_type, std::any::type_name::<Flaky>());
+        assert_eq!(failed[0].panic.as_deref(), Some("fail"));
+        assert_eq!(failed[0].restarts, 4);
+    }
+}
+
+#[cfg(test)]
+mod watch_tests {
+    use std::time::Duration;
+
+    use crate::{
+        Actor, ActorConfig, ActorSystem, Addr, Ctx, Handler, Mailbox, Message, Sender, StopReason,
+        Stoppable, SupervisionStrategy, Terminated,
+    };
+    use std::num::NonZeroUsize;
+    use std::sync::Arc;
+    use tokio::sync::{mpsc, Notify};
+
+    struct Peer;
+
+    impl Actor for Peer {}
+
+    #[derive(Message)]
+    struct Quit;
+
+    #[derive(Message)]
+    struct Crash;
+
+    impl Handler<Quit> for Peer {
+        async fn handle(&mut self, _: Quit, ctx: &Ctx<Self>) {
+            ctx.stop();
+        }
+    }
+
+    impl Handler<Crash> for Peer {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
+        }
+    }
+
+    struct Watcher {
+        terminated: mpsc::UnboundedSender<Terminated>,
+    }
+
+    impl Actor for Watcher {}
+
+    #[derive(Message)]
+    struct Watch(Addr<Peer>);
+
+    #[derive(Message)]
+    struct Unwatch(Addr<Peer>);
+
+    #[derive(Message)]
+    struct Block(Arc<Notify>);
+
+    impl Handler<Watch> for Watcher {
+        async fn handle(&mut self, msg: Watch, ctx: &Ctx<Self>) {
+            ctx.watch(&msg.0);
+        }
+    }
+
+    impl Handler<Unwatch> for Watcher {
+        async fn handle(&mut self, msg: Unwatch, ctx: &Ctx<Self>) {
+            ctx.unwatch(&msg.0);
+        }
+    }
+
+    impl Handler<Block> for Watcher {
+        async fn handle(&mut self, msg: Block, _: &Ctx<Self>) {
+            msg.0.notified().await;
+        }
+    }
+
+    impl Handler<Terminated> for Watcher {
+        async fn handle(&mut self, msg: Terminated, _: &Ctx<Self>) {
+            let _ = self.terminated.send(msg);
+        }
+    }
+
+    fn peer() -> Addr<Peer> {
+        ActorSystem::global().spawn_with_config(|| Peer, SupervisionStrategy::NoRestart)
+    }
+
+    #[tokio::test]
+    async fn watchers_receive_terminated() {
+        let (tx, mut terminated) = mpsc::unbounded_channel();
+        let watcher = Watcher { terminated: tx }.start();
+
+        let quitter = peer();
+        let crasher = peer();
+        watcher.ask(Watch(quitter.clone())).await;
+        watcher.ask(Watch(crasher.clone())).await;
+
+        quitter.tell(Quit);
+        let msg = terminated.recv().await.unwrap();
+        assert_eq!(msg.id, quitter.id());
+        assert_eq!(msg.reason, StopReason::Stopped);
+
+        crasher.tell(Crash);
+        let msg = terminated.recv().await.unwrap();
+        assert_eq!(msg.id, crasher.id());
+        assert_eq!(msg.reason, StopReason::Panicked);
+    }
+
+    #[tokio::test]
+    async fn unwatched_actors_are_not_reported() {
+        let (tx, mut terminated) = mpsc::unbounded_channel();
+        let watcher = Watcher { terminated: tx }.start();
+
+        let peer = peer();
+        watcher.ask(Watch(peer.clone())).await;
+        watcher.ask(Unwatch(peer.clone())).await;
+        peer.tell(Quit);
+        peer.wait_until_stopped().await;
+
+        let received = tokio::time::timeout(Duration::from_millis(50), terminated.recv()).await;
+        assert!(received.is_err(), "unexpected Terminated message");
+    }
+
+    #[tokio::test]
+    async fn terminated_waits_for_room_in_a_full_mailbox() {
+        let (tx, mut terminated) = mpsc::unbounded_channel();
+        let watcher = ActorSystem::global().spawn_with_config(
+            move || Watcher {
+                terminated: tx.clone(),
+            },
+            ActorConfig {
+                mailbox: Some(Mailbox::Bounded(NonZeroUsize::MIN)),
+                ..Default::default()
+            },
+        );
+        let other = peer();
+        let peer = peer();
+        watcher.ask(Watch(peer.clone())).await;
+
+        // Keep the watcher busy with one message and its mailbox full with
+        // another.
+        let release = Arc::new(Notify::new());
+        watcher.tell(Block(release.clone()));
+        while watcher.try_tell(Unwatch(other.clone())).is_err() {
+            tokio::task::yield_now().await;
+        }
+
+        peer.tell(Quit);
+        peer.wait_until_stopped().await;
+        release.notify_one();
+        let msg = terminated.recv().await.unwrap();
+        assert_eq!(msg.id, peer.id());
+        assert_eq!(msg.reason, StopReason::Stopped);
+    }
+}
+
+#[cfg(test)]
+mod link_tests {
+    use crate::{
+        Actor, ActorConfig, ActorSystem, Addr, Ctx, Exited, Handler, Mailbox, Message, Sender,
+        StopReason, Stoppable, SupervisionStrategy,
+    };
+    use std::num::NonZeroUsize;
+    use std::sync::Arc;
+    use tokio::sync::{mpsc, Notify};
+
+    struct Peer {
+        exits: Option<mpsc::UnboundedSender<Exited>>,
+    }
+
+    impl Actor for Peer {}
+
+    #[derive(Message)]
+    struct Link(Addr<Peer>);
+
+    #[derive(Message)]
+    struct TrapExits;
+
+    #[derive(Message)]
+    struct Quit;
+
+    #[derive(Message)]
+    struct Crash;
+
+    #[derive(Message)]
+    struct Block(Arc<Notify>);
+
+    impl Handler<Link> for Peer {
+        async fn handle(&mut self, msg: Link, ctx: &Ctx<Self>) {
+            ctx.link(&msg.0);
+        }
+    }
+
+    impl Handler<TrapExits> for Peer {
+        async fn handle(&mut self, _: TrapExits, ctx: &Ctx<Self>) {
+            ctx.trap_exit(true);
+        }
+    }
+
+    impl Handler<Quit> for Peer {
+        async fn handle(&mut self, _: Quit, ctx: &Ctx<Self>) {
+            ctx.stop();
+        }
+    }
+
+    impl Handler<Crash> for Peer {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
+        }
+    }
+
+    impl Handler<Block> for Peer {
+        async fn handle(&mut self, msg: Block, _: &Ctx<Self>) {
+            msg.0.notified().await;
+        }
+    }
+
+    impl Handler<Exited> for Peer {
+        async fn handle(&mut self, msg: Exited, _: &Ctx<Self>) {
+            if let Some(exits) = &self.exits {
+                let _ = exits.send(msg);
+            }
+        }
+    }
+
+    fn peer(exits: Option<mpsc::UnboundedSender<Exited>>) -> Addr<Peer> {
+        let mut exits = Some(exits);
+        ActorSystem::global().spawn_with_config(
+            move || Peer {
+                exits: exits.take().flatten(),
+            },
+            SupervisionStrategy::NoRestart,
+        )
+    }
+
+    #[tokio::test]
+    async fn abnormal_exits_stop_linked_actors() {
+        let a = peer(None);
+        let b = peer(None);
+        a.ask(Link(b.clone())).await;
+
+        a.tell(Crash);
+        b.wait_until_stopped().await;
+        assert_eq!(b.stop_reason(), Some(StopReason::Linked(a.id())));
+    }
+
+    #[tokio::test]
+    async fn normal_exits_do_not_stop_linked_actors() {
+        let a = peer(None);
+        let b = peer(None);
+        a.ask(Link(b.clone())).await;
+
+        a.tell(Quit);
+        a.wait_until_stopped().await;
+        assert!(b.is_alive());
+        b.ask(Link(a.clone())).await;
+        assert!(b.is_alive());
+    }
+
+    #[tokio::test]
+    async fn trapping_actors_receive_exited() {
+        let (tx, mut exits) = mpsc::unbounded_channel();
+        let a = peer(None);
+        let b = peer(Some(tx));
+        b.ask(TrapExits).await;
+        b.ask(Link(a.clone())).await;
+
+        a.tell(Crash);
+        let exited = exits.recv().await.unwrap();
+        assert_eq!(exited.id, a.id());
+        assert_eq!(exited.reason, StopReason::Panicked);
+        assert!(b.is_alive());
+    }
+
+    #[tokio::test]
+    async fn exited_waits_for_room_in_a_full_mailbox() {
+        let (tx, mut exits) = mpsc::unbounded_channel();
+        let a = peer(None);
+        let mut exits_tx = Some(tx);
+        let b = ActorSystem::global().spawn_with_config(
+            move || Peer {
+                exits: exits_tx.take(),
+            },
+            ActorConfig {
+                supervision: Some(SupervisionStrategy::NoRestart),
+                mailbox: Some(Mailbox::Bounded(NonZeroUsize::MIN)),
+                ..Default::default()
+            },
+        );
+        b.ask(TrapExits).await;
+        b.ask(Link(a.clone())).await;
+
+        // Keep b busy with one message and its mailbox full with another.
+        let release = Arc::new(Notify::new());
+        b.tell(Block(release.clone()));
+        while b.try_tell(TrapExits).is_err() {
+            tokio::task::yield_now().await;
+        }
+
+        a.tell(Crash);
+        a.wait_until_stopped().await;
+        release.notify_one();
+        let exited = exits.recv().await.unwrap();
+        assert_eq!(exited.id, a.id());
+    }
+}
+
+#[cfg(test)]
+mod stop_reason_tests {
+    use crate::{
+        Actor, ActorSystem, Addr, Ctx, Handler, Message, Sender, StopReason, Stoppable,
+        SupervisionStrategy,
+    };
+    use tokio::sync::mpsc;
+
+    struct Reporter {
+        reasons: mpsc::UnboundedSender<StopReason>,
+    }
+
+    impl Actor for Reporter {
+        async fn stopped(&mut self, reason: &StopReason, _: &Ctx<Self>) {
+            let _ = self.reasons.send(reason.clone());
+        }
+    }
+
+    #[derive(Message)]
+    struct Quit;
+
+    #[derive(Message)]
+    struct Crash;
+
+    #[derive(Message)]
+    #[response(Addr<Reporter>)]
+    struct SpawnChild;
+
+    impl Handler<Quit> for Reporter {
+        async fn handle(&mut self, _: Quit, ctx: &Ctx<Self>) {
+            ctx.stop();
+        }
+    }
+
+    impl Handler<Crash> for Reporter {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
+        }
+    }
+
+    impl Handler<SpawnChild> for Reporter {
+        async fn handle(&mut self, _: SpawnChild, ctx: &Ctx<Self>) -> Addr<Reporter> {
+            let reasons = self.reasons.clone();
+            ctx.spawn(move || Reporter {
+                reasons: reasons.clone(),
+            })
+        }
+    }
+
+    fn reporter(reasons: &mpsc::UnboundedSender<StopReason>) -> Addr<Reporter> {
+        let reasons = reasons.clone();
+        ActorSystem::global().spawn_with_config(
+            move || Reporter {
+                reasons: reasons.clone(),
+            },
+            SupervisionStrategy::NoRestart,
+        )
+    }
+
+    #[tokio::test]
+    async fn stop_reasons_reach_hooks_and_addresses() {
+        let (tx, mut reasons) = mpsc::unbounded_channel();
+
+        let quitter = reporter(&tx);
+        assert_eq!(quitter.stop_reason(), None);
+        quitter.tell(Quit);
+        quitter.wait_until_stopped().await;
+        assert_eq!(reasons.recv().await, Some(StopReason::Stopped));
+        assert_eq!(quitter.stop_reason(), Some(StopReason::Stopped));
+
+        let crasher = reporter(&tx);
+        crasher.tell(Crash);
+        crasher.wait_until_stopped().await;
+        assert_eq!(reasons.recv().await, Some(StopReason::Panicked));
+        assert_eq!(crasher.stop_reason(), Some(StopReason::Panicked));
+
+        let parent = reporter(&tx);
+        let child = parent.ask(SpawnChild).await;
+        parent.tell(Quit);
+        parent.wait_until_stopped().await;
+        assert_eq!(reasons.recv().await, Some(StopReason::ParentStopped));
+        assert_eq!(reasons.recv().await, Some(StopReason::Stopped));
+        assert_eq!(child.stop_reason(), Some(StopReason::ParentStopped));
+    }
+
+    #[tokio::test]
+    async fn dropping_every_address_stops_the_actor() {
+        let (tx, mut reasons) = mpsc::unbounded_channel();
+
+        // A parent holding its child in `Ctx::children` does not keep it
+        // running.
+        let parent = reporter(&tx);
+        drop(parent.ask(SpawnChild).await);
+        assert_eq!(reasons.recv().await, Some(StopReason::AddressesDropped));
+        assert!(parent.is_alive());
+
+        drop(parent);
+        assert_eq!(reasons.recv().await, Some(StopReason::AddressesDropped));
+    }
+
+    struct Doomed;
+
+    impl Actor for Doomed {
+        async fn started(&mut self, _: &Ctx<Self>) {
+            panic!("cannot start");
+        }
+    }
+
+    #[tokio::test]
+    async fn panicking_hooks_record_a_stop_reason() {
+        let system = ActorSystem::new(Default::default());
+        let doomed = system.spawn(|| Doomed);
+        doomed.wait_until_stopped().await;
+        assert_eq!(doomed.stop_reason(), Some(StopReason::Panicked));
+        system.shutdown().await;
+    }
+}
+
+#[cfg(test)]
+mod pre_restart_tests {
+    use crate::{
+        Actor, ActorSystem, Backoff, Ctx, Handler, Message, PanicInfo, Sender, SupervisionStrategy,
+    };
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Started(u64),
+        PreRestart(u64, String, &'static str),
+    }
+
+    struct Flaky {
+        generation: u64,
+        events: mpsc::UnboundedSender<Event>,
+    }
+
+    impl Actor for Flaky {
+        async fn started(&mut self, _: &Ctx<Self>) {
+            let _ = self.events.send(Event::Started(self.generation));
+        }
+
+        async fn pre_restart(&mut self, panic: &PanicInfo, _: &Ctx<Self>) {
+            let _ = self.events.send(Event::PreRestart(
+                self.generation,
+                panic.message().to_owned(),
+                panic.message_type(),
+            ));
+        }
+    }
+
+    #[derive(Message)]
+    struct Crash(String);
+
+    impl Handler<Crash> for Flaky {
+        async fn handle(&mut self, msg: Crash, _: &Ctx<Self>) {
+            panic!("{}", msg.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn pre_restart_sees_the_panic_before_the_replacement_starts() {
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let generations = Arc::new(AtomicU64::new(0));
+        let addr = ActorSystem::global().spawn(move || Flaky {
+            generation: generations.fetch_add(1, Ordering::SeqCst),
+            events: tx.clone(),
+        });
+
+        addr.tell(Crash("disk full".into()));
+
+        assert_eq!(events.recv().await, Some(Event::Started(0)));
+        assert_eq!(
+            events.recv().await,
+            Some(Event::PreRestart(
+                0,
+                "disk full".into(),
+                std::any::type_name::<Crash>()
+            ))
+        );
+        assert_eq!(events.recv().await, Some(Event::Started(1)));
+    }
+
+    #[tokio::test]
+    async fn pre_restart_runs_before_the_backoff() {
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let generations = Arc::new(AtomicU64::new(0));
+        let system = ActorSystem::new(Default::default());
+        let addr = system.spawn_with_config(
+            move || Flaky {
+                generation: generations.fetch_add(1, Ordering::SeqCst),
+                events: tx.clone(),
+            },
+            SupervisionStrategy::Restart {
+                window: Duration::from_secs(5),
+                max_restarts: 5,
+                backoff: Some(Backoff {
+                    min: Duration::from_secs(60),
+                    ..Default::default()
+                }),
+            },
+        );
+
+        addr.tell(Crash("disk full".into()));
+        assert_eq!(events.recv().await, Some(Event::Started(0)));
+        assert!(matches!(
+            events.recv().await,
+            Some(Event::PreRestart(0, _, _))
+        ));
+
+        // Stopping during the backoff does not lose the hook.
+        assert!(system.shutdown().await.is_clean());
+        assert_eq!(events.recv().await, None);
+    }
+}
+
+#[cfg(test)]
+mod handoff_tests {
+    use crate::{Actor, ActorSystem, Ctx, Handler, Message, Sender, SupervisionStrategy};
+
+    /// Keeps its processed count across restarts but rebuilds its buffer.
+    struct Tally {
+        processed: u64,
+        buffer: Vec<u64>,
+    }
+
+    impl Actor for Tally {}
+
+    #[derive(Message)]
+    struct Add(u64);
+
+    #[derive(Message)]
+    struct Crash;
+
+    #[derive(Message)]
+    #[response((u64, usize))]
+    struct Report;
+
+    impl Handler<Add> for Tally {
+        async fn handle(&mut self, msg: Add, _: &Ctx<Self>) {
+            self.processed += 1;
+            self.buffer.push(msg.0);
+        }
+    }
+
+    impl Handler<Crash> for Tally {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
+        }
+    }
+
+    impl Handler<Report> for Tally {
+        async fn handle(&mut self, _: Report, _: &Ctx<Self>) -> (u64, usize) {
+            (self.processed, self.buffer.len())
+        }
+    }
+
+    #[tokio::test]
+    async fn restart_factory_receives_the_previous_instance() {
+        let addr = ActorSystem::global().spawn_with_handoff(
+            |previous: Option<Tally>| Tally {
+                processed: previous.map_or(0, |tally| tally.processed),
+                buffer: Vec::new(),
+            },
+            SupervisionStrategy::default(),
+        );
+
+        addr.tell(Add(1));
+        addr.tell(Add(2));
+        addr.tell(Crash);
+        addr.tell(Add(3));
+
+        assert_eq!(addr.ask(Report).await, (3, 1));
+    }
+}
+
+#[cfg(test)]
+mod failure_reporter_tests {
+    use crate::{
+        Actor, ActorSystem, Addr, ChildFailure, Ctx, FailureEvent, Handler, Interrupt, Message,
+        Sender, SupervisionStrategy,
+    };
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    struct Fragile;
+
+    impl Actor for Fragile {}
+
+    #[derive(Message)]
+    struct Crash;
+
+    impl Handler<Crash> for Fragile {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("fragile");
+        }
+    }
+
+    /// Spawns `Fragile` children and shrugs off their escalations.
+    struct Parent;
+
+    impl Actor for Parent {
+        async fn child_escalated(&mut self, _: &ChildFailure, _: &Ctx<Self>) -> Option<Interrupt> {
+            None
+        }
+    }
+
+    #[derive(Message)]
+    #[response(Addr<Fragile>)]
+    struct SpawnFragile;
+
+    impl Handler<SpawnFragile> for Parent {
+        async fn handle(&mut self, _: SpawnFragile, ctx: &Ctx<Self>) -> Addr<Fragile> {
+            ctx.spawn_with_config(
+                || Fragile,
+                SupervisionStrategy::Restart {
+                    window: Duration::from_secs(5),
+                    max_restarts: 1,
+                    backoff: None,
+                },
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn panics_restarts_and_escalations_reach_the_reporter() {
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let system = ActorSystem::builder()
+            .failure_reporter(move |event: &FailureEvent| {
+                let _ = tx.send(event.clone());
+            })
+            .build();
+
+        let parent = Parent.start_in(&system);
+        let addr = parent.ask(SpawnFragile).await;
+        addr.tell(Crash);
+        addr.tell(Crash);
+
+        let mut seen = Vec::new();
+        while seen.len() < 4 {
+            seen.push(events.recv().await.unwrap());
+        }
+
+        assert!(
+            matches!(&seen[0], FailureEvent::Panicked { id, panic, .. } if *id == addr.id() && panic.message() == "fragile")
+        );
+        assert!(matches!(
+            seen[1],
+            FailureEvent::Restarting {
+                restarts: 0,
+                delay: Duration::ZERO,
+                ..
+            }
+        ));
+        assert!(matches!(seen[2], FailureEvent::Panicked { .. }));
+        assert!(
+            matches!(&seen[3], FailureEvent::Escalated(failure) if failure.id == addr.id() && failure.restarts == 1)
+        );
+        system.shutdown().await;
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tracing_tests {
+    use crate::{Actor, Ctx, Handler, Message, Sender};
+    use std::collections::HashMap;
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    };
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Instrument, Metadata, Subscriber,
+    };
+
+    type SpanRecord = (&'static Metadata<'static>, Option<String>);
+
+    /// Records spans with their `message.kind` field, follows-from links and
+    /// the stack of entered spans.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        next_id: Arc<AtomicU64>,
+        spans: Arc<Mutex<HashMap<u64, SpanRecord>>>,
+        follows: Arc<Mutex<Vec<(u64, u64)>>>,
+        entered: Arc<Mutex<Vec<u64>>>,
+    }
+
+    struct MessageKind(Option<String>);
+
+    impl Visit for MessageKind {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "message.kind" {
+                self.0 = Some(value.to_owned());
+            }
+        }
+
+        fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+            let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+            let mut kind = MessageKind(None);
+            attrs.record(&mut kind);
+            self.spans
+                .lock()
+                .unwrap()
+                .insert(id, (attrs.metadata(), kind.0));
+            span::Id::from_u64(id)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, span: &span::Id, follows: &span::Id) {
+            self.follows
+                .lock()
+                .unwrap()
+                .push((span.into_u64(), follows.into_u64()));
+        }
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, span: &span::Id) {
+            self.entered.lock().unwrap().push(span.into_u64());
+        }
+
+        fn exit(&self, _: &span::Id) {
+            self.entered.lock().unwrap().pop();
+        }
+
+        fn current_span(&self) -> tracing_core::span::Current {
+            match self.entered.lock().unwrap().last() {
+                Some(&id) => {
+                    let metadata = self.spans.lock().unwrap()[&id].0;
+                    tracing_core::span::Current::new(span::Id::from_u64(id), metadata)
+                }
+                None => tracing_core::span::Current::none(),
+            }
+        }
+    }
+
+    struct Traced;
+
+    impl Actor for Traced {}
+
+    #[derive(Message)]
+    struct Ping;
+
+    impl Handler<Ping> for Traced {
+        async fn handle(&mut self, _: Ping, _: &Ctx<Self>) {}
+    }
+
+    #[tokio::test]
+    async fn handler_span_follows_from_the_callers_span() {
+        let recorder = Recorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
 
-impl<A: Actor> Addr<A> {
-    /// Wait until the actor task has fully stopped.
-    pub async fn wait_until_stopped(&self) {
-        self.stopped.cancelled().await;
+        let addr = Traced.start();
+        let caller = tracing::info_span!("caller");
+        let caller_id = caller.id().unwrap().into_u64();
+        addr.ask(Ping).instrument(caller).await;
+
+        let spans = recorder.spans.lock().unwrap();
+        let (handler_id, _) = spans
+            .iter()
+            .find(|(_, (metadata, kind))| {
+                metadata.name() == "handle"
+                    && kind.as_deref() == Some(std::any::type_name::<Ping>())
+            })
+            .expect("handler span recorded");
+        assert!(recorder
+            .follows
+            .lock()
+            .unwrap()
+            .contains(&(*handler_id, caller_id)));
     }
 }
 
-impl<A> Clone for Addr<A>
-where
-    A: Actor,
-{
-    fn clone(&self) -> Self {
-        Addr {
-            tx: self.tx.clone(),
-            stopped: self.stopped.clone(),
-        }
+#[cfg(all(test, feature = "metrics"))]
+mod metrics_tests {
+    use crate::{Actor, ActorSystem, Ctx, Handler, Histogram, Message, MetricsRegistry, Sender};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Notify;
+
+    struct Worker {
+        gate: Arc<Notify>,
     }
-}
 
-/// Capability to send messages of type `M` to an actor.
-///
-/// Implemented by both [`Addr`] and [`Recipient`].
-#[async_trait]
-pub trait Sender<M>
-where
-    M: Message,
-{
-    /// Send a message and await the response.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the actor has stopped before sending a response.
-    async fn ask(&self, msg: M) -> M::Response;
-    /// Send a message without waiting for a response (fire-and-forget).
-    fn tell(&self, msg: M);
-    /// Convert this sender into a type-erased [`Recipient`].
-    ///
-    /// This is useful for dependency injection: a `Recipient<M>` does not
-    /// expose the actor type.
-    fn recipient(self) -> Recipient<M>
-    where
-        Self: Sized + Send + Sync + 'static,
-    {
-        Recipient { tx: Box::new(self) }
+    impl Actor for Worker {}
+
+    #[derive(Message)]
+    struct Work;
+
+    #[derive(Message)]
+    struct Wait;
+
+    #[derive(Message)]
+    struct Crash;
+
+    impl Handler<Work> for Worker {
+        async fn handle(&mut self, _: Work, _: &Ctx<Self>) {}
     }
-}
 
-#[async_trait]
-impl<M, A> Sender<M> for Addr<A>
-where
-    M: Message,
-    A: Actor + Handler<M>,
-{
-    async fn ask(&self, msg: M) -> M::Response {
-        let (tx, rx) = oneshot::channel();
-        let _ = self.tx.send(Envelope::new(Some(msg), Some(tx)));
-        rx.await.expect("actor dropped before responding")
+    impl Handler<Wait> for Worker {
+        async fn handle(&mut self, _: Wait, _: &Ctx<Self>) {
+            self.gate.notified().await;
+        }
     }
-    fn tell(&self, msg: M) {
-        let _ = self.tx.send(Envelope::new(Some(msg), None));
+
+    impl Handler<Crash> for Worker {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
+        }
     }
-}
 
-/// Type-erased sender for a specific message type.
-///
-/// Wraps any [`Sender<M>`] behind a trait object so the concrete actor type
-/// is hidden. Useful for dependency injection where you want to expose only
-/// the ability to send a particular message.
-pub struct Recipient<M: Message> {
-    tx: Box<dyn Sender<M> + Send + Sync + 'static>,
-}
+    #[tokio::test]
+    async fn stats_track_mailbox_handlers_panics_and_restarts() {
+        let registry = MetricsRegistry::new();
+        let system = ActorSystem::builder()
+            .metrics_exporter(registry.clone())
+            .build();
 
-impl<M> Recipient<M>
-where
-    M: Message,
-{
-    /// Create a new `Recipient` from a boxed [`Sender`].
-    pub fn new(tx: Box<dyn Sender<M> + Send + Sync + 'static>) -> Self {
-        Recipient { tx }
+        let gate = Arc::new(Notify::new());
+        let addr = system.spawn({
+            let gate = gate.clone();
+            move || Worker { gate: gate.clone() }
+        });
+
+        addr.tell(Wait);
+        tokio::task::yield_now().await;
+        addr.tell(Work);
+        addr.tell(Crash);
+        assert_eq!(addr.stats().mailbox_len, 2);
+
+        gate.notify_one();
+        addr.ask(Work).await;
+
+        let stats = addr.stats();
+        assert_eq!(stats.mailbox_len, 0);
+        assert_eq!(stats.processed, 3);
+        assert_eq!(stats.panics, 1);
+        assert_eq!(stats.restarts, 1);
+        assert_eq!(stats.handlers[std::any::type_name::<Work>()].count(), 2);
+        assert_eq!(stats.handlers[std::any::type_name::<Wait>()].count(), 1);
+        assert_eq!(registry.get(addr.id()), Some(stats));
+        system.shutdown().await;
+    }
+
+    #[test]
+    fn histogram_buckets_by_upper_bound() {
+        let mut histogram = Histogram::default();
+        histogram.record(Duration::from_micros(5));
+        histogram.record(Duration::from_micros(10));
+        histogram.record(Duration::from_millis(50));
+        histogram.record(Duration::from_secs(20));
+
+        assert_eq!(histogram.buckets(), &[2, 0, 0, 0, 1, 0, 0, 1]);
+        assert_eq!(histogram.count(), 4);
+        assert_eq!(histogram.max(), Duration::from_secs(20));
+        assert_eq!(histogram.mean(), histogram.sum() / 4);
     }
 }
 
-#[async_trait]
-impl<M> Sender<M> for Recipient<M>
-where
-    M: Message,
-{
-    async fn ask(&self, msg: M) -> M::Response {
-        self.tx.ask(msg).await
+#[cfg(test)]
+mod system_tests {
+    use crate::{
+        Actor, ActorSystem, ActorSystemConfig, Ctx, Handler, Message, Sender, StopReason,
+        SupervisionStrategy,
+    };
+    use std::time::Duration;
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    struct Echo;
+
+    impl Actor for Echo {}
+
+    #[derive(Message)]
+    #[response(u32)]
+    struct Ping(u32);
+
+    #[derive(Message)]
+    struct Crash;
+
+    impl Handler<Ping> for Echo {
+        async fn handle(&mut self, msg: Ping, _: &Ctx<Self>) -> u32 {
+            msg.0
+        }
     }
 
-    fn tell(&self, msg: M) {
-        self.tx.tell(msg);
+    impl Handler<Crash> for Echo {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
+        }
     }
-}
 
-#[async_trait]
-impl<A> Stoppable for Ctx<A>
-where
-    A: Actor,
-{
-    fn stop(&self) {
-        self.cancel.cancel();
+    #[tokio::test]
+    async fn systems_shut_down_independently() {
+        let first = ActorSystem::new(ActorSystemConfig::default());
+        let second = ActorSystem::new(ActorSystemConfig::default());
+        let a = Echo.start_in(&first);
+        let b = Echo.start_in(&second);
+
+        first.shutdown().await;
+        assert_eq!(a.stop_reason(), Some(StopReason::ParentStopped));
+        assert!(b.is_alive());
+        assert_eq!(b.ask(Ping(7)).await, 7);
+
+        let third = ActorSystem::new(ActorSystemConfig::default());
+        assert_eq!(Echo.start_in(&third).ask(Ping(8)).await, 8);
+
+        second.shutdown().await;
+        third.shutdown().await;
     }
 
-    async fn wait_until_stopped(&self) {
-        self.stopped.cancelled().await;
+    #[tokio::test]
+    async fn dead_letters_stay_within_their_system() {
+        let first = ActorSystem::new(ActorSystemConfig::default());
+        let second = ActorSystem::new(ActorSystemConfig::default());
+        let mut first_letters = first.dead_letters();
+        let mut second_letters = second.dead_letters();
+
+        let addr = Echo.start_in(&first);
+        first.shutdown().await;
+        addr.tell(Ping(1));
+
+        let letter = first_letters.recv().await.unwrap();
+        assert_eq!(letter.message, std::any::type_name::<Ping>());
+        assert_eq!(second_letters.try_recv().unwrap_err(), TryRecvError::Empty);
+
+        second.shutdown().await;
     }
 
-    async fn stop_all_children(&self) {
-        let children: Vec<_> = self.children.lock().unwrap().drain(..).collect();
-        for child in children {
-            child.stop();
-            child.wait_until_stopped().await;
-        }
+    #[tokio::test]
+    async fn escalations_do_not_take_down_the_system() {
+        let system = ActorSystem::new(ActorSystemConfig::default());
+        let sibling = Echo.start_in(&system);
+        let fragile = system.spawn_with_config(
+            || Echo,
+            SupervisionStrategy::Restart {
+                window: Duration::from_secs(5),
+                max_restarts: 0,
+                backoff: None,
+            },
+        );
+
+        fragile.tell(Crash);
+        fragile.wait_until_stopped().await;
+        assert_eq!(fragile.stop_reason(), Some(StopReason::RestartsExhausted));
+        assert_eq!(sibling.ask(Ping(1)).await, 1);
+        assert_eq!(Echo.start_in(&system).ask(Ping(2)).await, 2);
+        assert_eq!(system.snapshot_tree().restarts, 0);
+
+        system.shutdown().await;
     }
 }
 
 #[cfg(test)]
-mod simple_tests {
-    use crate::{Actor, Addr, Ctx, Handler, Message, Sender, Stoppable};
-    use std::time::Instant;
+mod builder_tests {
+    use crate::{
+        Actor, ActorSystem, Ctx, Handler, Mailbox, Message, SendError, Sender, StopReason,
+        SupervisionStrategy,
+    };
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
 
-    #[tokio::test(flavor = "multi_thread")]
-    async fn simple_counter() -> anyhow::Result<()> {
-        // Simple counter
-        struct Counter {
-            count: i64,
-        }
+    struct Sleeper;
 
-        impl Actor for Counter {}
+    impl Actor for Sleeper {}
 
-        #[derive(Message)]
-        struct Increment;
+    #[derive(Message)]
+    struct Hang;
 
-        #[derive(Message)]
-        struct Decrement;
+    #[derive(Message)]
+    struct Crash;
 
-        #[derive(Message)]
-        #[response(i64)]
-        struct GetCount;
+    #[derive(Message)]
+    #[response(Option<String>)]
+    struct ThreadName;
 
-        impl Handler<Increment> for Counter {
-            async fn handle(&mut self, _: Increment, _: &Ctx<Self>) {
-                self.count += 1;
-            }
+    impl Handler<Hang> for Sleeper {
+        async fn handle(&mut self, _: Hang, _: &Ctx<Self>) {
+            std::future::pending::<()>().await;
         }
+    }
 
-        impl Handler<Decrement> for Counter {
-            async fn handle(&mut self, _: Decrement, _: &Ctx<Self>) {
-                self.count -= 1;
-            }
+    impl Handler<Crash> for Sleeper {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
         }
+    }
 
-        impl Handler<GetCount> for Counter {
-            async fn handle(&mut self, _: GetCount, _: &Ctx<Self>) -> i64 {
-                self.count
-            }
+    impl Handler<ThreadName> for Sleeper {
+        async fn handle(&mut self, _: ThreadName, _: &Ctx<Self>) -> Option<String> {
+            std::thread::current().name().map(str::to_owned)
         }
+    }
 
-        let counter = Counter { count: 0 }.start();
+    #[tokio::test]
+    async fn spawns_use_the_system_defaults() {
+        let system = ActorSystem::builder()
+            .mailbox(Mailbox::Bounded(NonZeroUsize::MIN))
+            .supervision(SupervisionStrategy::NoRestart)
+            .build();
 
-        let mut handles = vec![];
+        let child = system.spawn(|| Sleeper);
+        child.tell(Hang);
+        tokio::task::yield_now().await;
+        assert!(child.try_tell(Hang).is_ok());
+        assert!(matches!(child.try_tell(Hang), Err(SendError::Full(_))));
 
-        let t1 = counter.clone();
-        let t2 = counter.clone();
+        let fragile = system.spawn(|| Sleeper);
+        fragile.tell(Crash);
+        fragile.wait_until_stopped().await;
+        assert_eq!(fragile.stop_reason(), Some(StopReason::Panicked));
+    }
 
-        let total = 10_000_000;
-        let start = Instant::now();
-        handles.push(tokio::task::spawn(async move {
-            for _ in 0..(total / 2) {
-                t1.tell(Increment);
-            }
-            Ok::<_, anyhow::Error>(())
-        }));
-        handles.push(tokio::task::spawn(async move {
-            for _ in 0..(total / 2) {
-                t2.tell(Decrement);
-            }
-            Ok::<_, anyhow::Error>(())
-        }));
-        for handle in handles {
-            handle.await??;
-        }
-        let count = counter.ask(GetCount).await;
-        assert_eq!(count, 0);
-        let finished = start.elapsed();
-        let msg_per_sec = total as f64 / finished.as_secs_f64();
-        println!("{:.1} million msg/sec", msg_per_sec / 1_000_000.0);
-        Ok(())
+    #[tokio::test]
+    async fn start_in_uses_the_system_supervision() {
+        let system = ActorSystem::builder()
+            .supervision(SupervisionStrategy::NoRestart)
+            .build();
+        let fragile = Sleeper.start_in(&system);
+        fragile.tell(Crash);
+        fragile.wait_until_stopped().await;
+        assert_eq!(fragile.stop_reason(), Some(StopReason::Panicked));
+
+        let system = ActorSystem::builder().build();
+        let sturdy = Sleeper.start_in(&system);
+        sturdy.tell(Crash);
+        // Answered by the same instance once it has been restarted.
+        sturdy.ask(ThreadName).await;
+        assert!(sturdy.is_alive());
+        assert_eq!(sturdy.stop_reason(), None);
     }
 
     #[tokio::test]
-    async fn restart_counter() -> anyhow::Result<()> {
-        struct Db {
-            value: i64,
-        }
+    async fn actors_run_on_the_configured_runtime() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("tactix-test-runtime")
+            .enable_all()
+            .build()
+            .unwrap();
+        let system = ActorSystem::builder()
+            .runtime(runtime.handle().clone())
+            .build();
 
-        impl Actor for Db {
-            async fn stopped(&mut self, _: &Ctx<Self>) {
-                println!("Db stopped");
-            }
-        }
+        let addr = system.spawn(|| Sleeper);
+        assert_eq!(
+            addr.ask(ThreadName).await.as_deref(),
+            Some("tactix-test-runtime")
+        );
 
-        #[derive(Message)]
-        #[response(i64)]
-        struct DbGet;
+        system.shutdown().await;
+        runtime.shutdown_background();
+    }
 
-        #[derive(Message)]
-        struct DbSet(i64);
+    #[tokio::test]
+    async fn shutdown_aborts_actors_after_the_timeout() {
+        let system = ActorSystem::builder()
+            .shutdown_timeout(Duration::from_millis(50))
+            .build();
+        let stuck = system.spawn(|| Sleeper);
+        stuck.tell(Hang);
+        tokio::task::yield_now().await;
 
-        impl Handler<DbGet> for Db {
-            async fn handle(&mut self, _: DbGet, _: &Ctx<Self>) -> i64 {
-                self.value
-            }
+        let report = tokio::time::timeout(Duration::from_secs(5), system.shutdown())
+            .await
+            .expect("shutdown returns once the timeout elapses");
+        assert!(!stuck.is_alive());
+        assert_eq!(stuck.stop_reason(), Some(StopReason::Aborted));
+        assert!(report
+            .aborted
+            .iter()
+            .any(|aborted| aborted.id == stuck.id()));
+    }
+}
+
+#[cfg(test)]
+mod shutdown_tests {
+    use crate::{
+        Actor, ActorConfig, ActorSystem, Ctx, Handler, Message, Sender, ShutdownPhase, StopReason,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// Takes `linger` to run its `stopped` hook.
+    struct Slow {
+        linger: Duration,
+    }
+
+    impl Actor for Slow {
+        async fn stopped(&mut self, _: &StopReason, _: &Ctx<Self>) {
+            tokio::time::sleep(self.linger).await;
         }
+    }
 
-        impl Handler<DbSet> for Db {
-            async fn handle(&mut self, msg: DbSet, _: &Ctx<Self>) {
-                self.value = msg.0;
-            }
+    #[derive(Message)]
+    struct Hang;
+
+    impl Handler<Hang> for Slow {
+        async fn handle(&mut self, _: Hang, _: &Ctx<Self>) {
+            std::future::pending::<()>().await;
         }
+    }
 
-        struct Counter {
-            db: Addr<Db>,
+    #[tokio::test]
+    async fn children_stop_in_parallel_and_laggards_are_aborted() {
+        let system = ActorSystem::builder()
+            .stop_timeout(Duration::from_secs(5))
+            .build();
+        let linger = Duration::from_millis(200);
+        let slow: Vec<_> = (0..3)
+            .map(|_| system.spawn(move || Slow { linger }))
+            .collect();
+        let stuck = system.spawn_with_config(
+            move || Slow { linger },
+            ActorConfig {
+                stop_timeout: Some(Duration::from_millis(50)),
+                ..Default::default()
+            },
+        );
+        stuck.tell(Hang);
+        tokio::task::yield_now().await;
+
+        let started = Instant::now();
+        let report = system.shutdown().await;
+        assert!(
+            started.elapsed() < linger * 2,
+            "took {:?}",
+            started.elapsed()
+        );
+
+        for addr in &slow {
+            assert_eq!(addr.stop_reason(), Some(StopReason::ParentStopped));
         }
+        assert_eq!(stuck.stop_reason(), Some(StopReason::Aborted));
+        assert_eq!(report.aborted.len(), 1);
+        assert_eq!(report.aborted[0].id, stuck.id());
+        assert_eq!(report.aborted[0].timeout, Duration::from_millis(50));
+        assert!(report.timed_out_tasks.is_empty());
+    }
 
-        impl Actor for Counter {
-            async fn stopped(&mut self, _: &Ctx<Self>) {
-                println!("Counter stopped");
-            }
+    #[tokio::test]
+    async fn shutdown_tasks_run_in_phase_order() {
+        let system = ActorSystem::builder()
+            .shutdown_timeout(Duration::from_millis(100))
+            .build();
+        let actor = system.spawn(|| Slow {
+            linger: Duration::ZERO,
+        });
+        let log = Arc::new(Mutex::new(Vec::new()));
+        for (phase, name) in [
+            (ShutdownPhase::AfterActorsStop, "flush"),
+            (ShutdownPhase::BeforeActorsStop, "drain"),
+        ] {
+            let log = log.clone();
+            let actor = actor.clone();
+            system.add_shutdown_task(phase, name, async move {
+                log.lock().unwrap().push((name, actor.is_alive()));
+            });
         }
+        system.add_shutdown_task(
+            ShutdownPhase::AfterActorsStop,
+            "hang",
+            std::future::pending(),
+        );
+
+        let report = system.shutdown().await;
+        assert_eq!(*log.lock().unwrap(), [("drain", true), ("flush", false)]);
+        assert_eq!(
+            report.timed_out_tasks,
+            [(ShutdownPhase::AfterActorsStop, "hang".to_owned())]
+        );
+        assert!(report.aborted.is_empty());
+    }
+}
 
-        #[derive(Message)]
-        #[response(i64)]
-        struct Increment;
+#[cfg(test)]
+mod signal_tests {
+    use crate::{Actor, ActorSystem, Ctx, Handler, Message, Sender, StopReason};
+    use std::time::Duration;
 
-        #[derive(Message)]
-        #[response(i64)]
-        struct GetCount;
+    struct Stuck;
 
-        #[derive(Message)]
-        struct Poison;
+    impl Actor for Stuck {}
 
-        #[derive(Message)]
-        struct Stop;
+    #[derive(Message)]
+    struct Hang;
 
-        impl Handler<Increment> for Counter {
-            async fn handle(&mut self, _: Increment, _: &Ctx<Self>) -> i64 {
-                let count = self.db.ask(DbGet).await + 1;
-                self.db.tell(DbSet(count));
-                count
-            }
+    impl Handler<Hang> for Stuck {
+        async fn handle(&mut self, _: Hang, _: &Ctx<Self>) {
+            std::future::pending::<()>().await;
         }
+    }
 
-        impl Handler<GetCount> for Counter {
-            async fn handle(&mut self, _: GetCount, _: &Ctx<Self>) -> i64 {
-                self.db.ask(DbGet).await
-            }
-        }
+    #[tokio::test]
+    async fn first_signal_shuts_down_and_second_aborts() {
+        let system = ActorSystem::new(Default::default());
+        let stuck = system.spawn(|| Stuck);
+        stuck.tell(Hang);
+        tokio::task::yield_now().await;
 
-        impl Handler<Poison> for Counter {
-            async fn handle(&mut self, _: Poison, _: &Ctx<Self>) {
-                panic!("poisoned!");
+        let (raise, signals) = futures::channel::mpsc::unbounded();
+        let run = system.run_until(signals);
+        tokio::pin!(run);
+        assert!(futures::poll!(&mut run).is_pending());
+
+        raise.unbounded_send(()).unwrap();
+        let root = system.address();
+        while !root.is_closed() {
+            tokio::select! {
+                _ = &mut run => panic!("shutdown cannot finish while an actor hangs"),
+                _ = tokio::time::sleep(Duration::from_millis(10)) => {}
             }
         }
+        assert!(stuck.is_alive());
 
-        struct Root {}
+        raise.unbounded_send(()).unwrap();
+        let report = tokio::time::timeout(Duration::from_secs(5), run)
+            .await
+            .expect("second signal forces shutdown");
+        assert_eq!(stuck.stop_reason(), Some(StopReason::Aborted));
+        assert!(report
+            .aborted
+            .iter()
+            .any(|aborted| aborted.id == stuck.id()));
+    }
+}
 
-        impl Actor for Root {
-            async fn stopped(&mut self, _: &Ctx<Self>) {
-                println!("Root stopped");
-            }
-        }
+#[cfg(test)]
+mod identity_tests {
+    use crate::{Actor, ActorId, ActorSystem, Ctx, Handler, Message, Recipient, Sender};
+    use std::collections::HashSet;
 
-        impl Handler<Stop> for Root {
-            async fn handle(&mut self, _: Stop, ctx: &Ctx<Self>) {
-                println!("in Stop handler");
-                ctx.stop();
-            }
-        }
+    struct Fragile;
 
-        #[derive(Message)]
-        #[response(Addr<Counter>)]
-        struct GetCounter;
+    impl Actor for Fragile {}
 
-        impl Handler<GetCounter> for Root {
-            async fn handle(&mut self, _: GetCounter, ctx: &Ctx<Self>) -> Addr<Counter> {
-                let db = ctx.spawn(|| Db { value: 0 });
-                let counter = ctx.spawn(move || Counter { db: db.clone() });
-                counter
-            }
-        }
+    #[derive(Message)]
+    struct Crash;
 
-        let root = Root {}.start();
+    #[derive(Message)]
+    #[response(ActorId)]
+    struct WhoAmI;
 
-        let counter = root.ask(GetCounter).await;
-        for _ in 0..5 {
-            counter.tell(Increment);
+    impl Handler<Crash> for Fragile {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
         }
-        assert_eq!(counter.ask(GetCount).await, 5);
+    }
 
-        counter.tell(Poison);
-        counter.ask(Increment).await;
+    impl Handler<WhoAmI> for Fragile {
+        async fn handle(&mut self, _: WhoAmI, ctx: &Ctx<Self>) -> ActorId {
+            ctx.id()
+        }
+    }
 
-        let count = counter.ask(GetCount).await;
-        assert_eq!(count, 6, "state survives because it lives in the db actor");
-        root.tell(Stop);
-        println!("just called stop!");
-        root.wait_until_stopped().await;
-        println!("goodbye");
-        Ok(())
+    #[tokio::test]
+    // `Addr` hashes only its id, which never changes.
+    #[allow(clippy::mutable_key_type)]
+    async fn addresses_compare_by_actor() {
+        let system = ActorSystem::new(Default::default());
+        let a = system.spawn(|| Fragile);
+        let b = system.spawn(|| Fragile);
+
+        let subscribers: HashSet<_> = [a.clone(), b.clone(), a.clone()].into_iter().collect();
+        assert_eq!(subscribers.len(), 2);
+        assert_ne!(a, b);
+        assert!(format!("{a:?}").contains(&format!("{:?}", a.id())));
+
+        let recipient: Recipient<WhoAmI> = a.clone().recipient();
+        assert_eq!(recipient.id(), a.id());
+        assert_eq!(recipient, a.clone().recipient());
+        assert_ne!(recipient, b.clone().recipient());
+        assert_eq!(Recipient::new(Box::new(a.clone())), recipient);
+        assert_eq!(Recipient::new(Box::new(a.clone().recipient())), recipient);
+        system.shutdown().await;
     }
-}
 
-//////////////////////////
+    #[tokio::test]
+    async fn id_is_stable_across_restarts() {
+        let system = ActorSystem::new(Default::default());
+        let addr = system.spawn(|| Fragile);
+        assert_eq!(addr.ask(WhoAmI).await, addr.id());
+        addr.tell(Crash);
+        assert_eq!(addr.ask(WhoAmI).await, addr.id());
+        system.shutdown().await;
+    }
+}
 
 #[cfg(test)]
-mod bank_tests {
-    use std::time::Duration;
+mod registry_tests {
+    use crate::{Actor, ActorSystem, Ctx, Handler, Message, NameError, Sender, Stoppable};
 
-    use crate::{Actor, Ctx, Handler, Message, Sender};
-    use tokio::time::sleep;
+    struct Orders;
 
-    struct Deposit(u64);
-    impl Message for Deposit {
-        type Response = ();
-    }
+    impl Actor for Orders {}
 
-    struct Withdraw(u64);
-    impl Message for Withdraw {
-        type Response = Result<(), String>;
-    }
+    struct Payments;
 
-    struct GetBalance;
-    impl Message for GetBalance {
-        type Response = u64;
+    impl Actor for Payments {}
+
+    #[derive(Message)]
+    #[response(bool)]
+    struct FindPayments;
+
+    #[derive(Message)]
+    struct Close;
+
+    impl Handler<Close> for Orders {
+        async fn handle(&mut self, _: Close, ctx: &Ctx<Self>) {
+            ctx.stop();
+        }
     }
 
-    struct GetAccountInfo;
-    impl Message for GetAccountInfo {
-        type Response = (u64, u64, u64);
+    impl Handler<FindPayments> for Orders {
+        async fn handle(&mut self, _: FindPayments, ctx: &Ctx<Self>) -> bool {
+            ctx.lookup::<Payments>("/system/payments").is_some()
+        }
     }
 
-    #[derive(Clone)]
-    struct BankAccount {
-        balance: u64,
-        total_deposits: u64,
-        total_withdrawals: u64,
+    #[tokio::test]
+    async fn named_actors_can_be_looked_up() {
+        let system = ActorSystem::new(Default::default());
+        let orders = system.spawn_named("orders", || Orders).unwrap();
+        assert_eq!(system.lookup::<Orders>("orders"), Some(orders.clone()));
+        assert_eq!(
+            system.lookup::<Orders>("/system/orders"),
+            Some(orders.clone())
+        );
+        assert_eq!(system.lookup::<Payments>("orders"), None);
+        assert_eq!(system.lookup::<Orders>("missing"), None);
+
+        assert!(!orders.ask(FindPayments).await);
+        system.spawn_named("payments", || Payments).unwrap();
+        assert!(orders.ask(FindPayments).await);
+        system.shutdown().await;
     }
 
-    impl BankAccount {
-        fn new(initial_balance: u64) -> Self {
-            Self {
-                balance: initial_balance,
-                total_deposits: 0,
-                total_withdrawals: 0,
+    #[tokio::test]
+    async fn names_are_unique_until_the_actor_stops() {
+        let system = ActorSystem::new(Default::default());
+        let first = system.spawn_named("orders", || Orders).unwrap();
+        assert_eq!(
+            system.spawn_named("orders", || Orders).unwrap_err(),
+            NameError::Taken {
+                name: "orders".to_owned(),
+                holder: first.id(),
             }
+        );
+        for name in ["", "a/b", "*", "$1"] {
+            assert_eq!(
+                system.spawn_named(name, || Orders).unwrap_err(),
+                NameError::Invalid(name.to_owned())
+            );
         }
+
+        system.lookup::<Orders>("orders").unwrap().tell(Close);
+        first.wait_until_stopped().await;
+        assert_eq!(system.lookup::<Orders>("orders"), None);
+        let second = system.spawn_named("orders", || Orders).unwrap();
+        assert_eq!(system.lookup::<Orders>("orders"), Some(second));
+        system.shutdown().await;
     }
+}
 
-    impl Actor for BankAccount {}
+#[cfg(test)]
+mod path_tests {
+    use crate::{Actor, ActorSystem, Addr, Ctx, Exposed, Handler, Message, Sender};
 
-    impl Handler<Deposit> for BankAccount {
-        async fn handle(&mut self, msg: Deposit, _: &Ctx<Self>) {
-            tokio::time::sleep(Duration::from_millis(6)).await;
-            self.balance += msg.0;
-            self.total_deposits += msg.0;
-            println!("Deposit: {}. New balance: {}", msg.0, self.balance);
-        }
+    struct Orders;
+
+    impl Actor for Orders {}
+
+    struct Worker {
+        pings: u64,
     }
-    impl Handler<Withdraw> for BankAccount {
-        async fn handle(&mut self, msg: Withdraw, _: &Ctx<Self>) -> Result<(), String> {
-            if self.balance >= msg.0 {
-                tokio::time::sleep(Duration::from_millis(10)).await;
-                self.balance -= msg.0;
-                self.total_withdrawals += msg.0;
-                println!("Withdrawal: {}. New balance: {}", msg.0, self.balance);
-                Ok(())
-            } else {
-                Err(format!(
-                    "Insufficient funds. Current balance: {}",
-                    self.balance
-                ))
-            }
+
+    impl Actor for Worker {
+        fn expose(exposed: &mut Exposed<Self>) {
+            exposed.message::<Ping>();
         }
     }
 
-    impl Handler<GetAccountInfo> for BankAccount {
-        async fn handle(&mut self, _msg: GetAccountInfo, _: &Ctx<Self>) -> (u64, u64, u64) {
-            println!("GetAccountInfo!");
-            (self.balance, self.total_deposits, self.total_withdrawals)
+    /// Handles `Ping` like a `Worker`, but is a different type.
+    struct Auditor;
+
+    impl Actor for Auditor {
+        fn expose(exposed: &mut Exposed<Self>) {
+            exposed.message::<Ping>();
         }
     }
-    impl Handler<GetBalance> for BankAccount {
-        async fn handle(&mut self, _msg: GetBalance, _: &Ctx<Self>) -> u64 {
-            self.balance
+
+    #[derive(Message)]
+    #[response(Vec<Addr<Worker>>)]
+    struct Hire(Vec<&'static str>);
+
+    #[derive(Message)]
+    #[response(Addr<Auditor>)]
+    struct HireAuditor;
+
+    #[derive(Message)]
+    #[response(usize)]
+    struct CountWorkers;
+
+    #[derive(Message, Clone)]
+    #[response(u64)]
+    struct Ping;
+
+    impl Handler<Hire> for Orders {
+        async fn handle(&mut self, Hire(names): Hire, ctx: &Ctx<Self>) -> Vec<Addr<Worker>> {
+            let mut workers: Vec<_> = names
+                .into_iter()
+                .map(|name| ctx.spawn_named(name, || Worker { pings: 0 }).unwrap())
+                .collect();
+            workers.push(ctx.spawn(|| Worker { pings: 0 }));
+            workers
         }
     }
 
-    #[tokio::test]
-    async fn test_bank_account_race_condition() {
-        let initial_balance = 1000;
-        let account = BankAccount::new(initial_balance).start();
+    impl Handler<HireAuditor> for Orders {
+        async fn handle(&mut self, _: HireAuditor, ctx: &Ctx<Self>) -> Addr<Auditor> {
+            ctx.spawn_named("auditor", || Auditor).unwrap()
+        }
+    }
 
-        let deposit_amount = 100;
-        let withdraw_amount = 200;
-        let num_operations = 5;
+    impl Handler<CountWorkers> for Orders {
+        async fn handle(&mut self, _: CountWorkers, ctx: &Ctx<Self>) -> usize {
+            ctx.select("*").addrs::<Worker>().len()
+        }
+    }
 
-        // Spawn multiple tasks to deposit and withdraw concurrently
-        let deposit_task = tokio::spawn({
-            let account = account.clone();
-            async move {
-                for _ in 0..num_operations {
-                    account.tell(Deposit(deposit_amount));
-                    sleep(Duration::from_millis(3)).await;
-                }
-            }
-        });
+    impl Handler<Ping> for Worker {
+        async fn handle(&mut self, _: Ping, _: &Ctx<Self>) -> u64 {
+            self.pings += 1;
+            self.pings
+        }
+    }
 
-        let withdraw_task = tokio::spawn({
-            let account = account.clone();
-            async move {
-                for _ in 0..num_operations {
-                    account.tell(Withdraw(withdraw_amount));
-                    sleep(Duration::from_millis(9)).await;
-                }
-            }
-        });
+    impl Handler<Ping> for Auditor {
+        async fn handle(&mut self, _: Ping, _: &Ctx<Self>) -> u64 {
+            1
+        }
+    }
 
-        // Wait for both tasks to complete
-        let _ = tokio::join!(deposit_task, withdraw_task);
+    #[tokio::test]
+    async fn paths_follow_the_actor_tree() {
+        let system = ActorSystem::new(Default::default());
+        let orders = system.spawn_named("orders", || Orders).unwrap();
+        let workers = orders.ask(Hire(vec!["worker-1"])).await;
 
-        // Get the final account info
-        let (final_balance, total_deposits, total_withdrawals) = account.ask(GetAccountInfo).await;
+        assert_eq!(system.path(), "/system");
+        assert_eq!(orders.path(), "/system/orders");
+        assert_eq!(workers[0].path(), "/system/orders/worker-1");
+        assert_eq!(
+            workers[1].path(),
+            format!("/system/orders/${}", workers[1].id())
+        );
+        system.shutdown().await;
+    }
 
-        // Assertions to check for race conditions
-        let expected_deposits = deposit_amount * num_operations;
-        let expected_withdrawals = withdraw_amount * num_operations;
-        let expected_balance = initial_balance + expected_deposits - expected_withdrawals;
+    #[tokio::test]
+    async fn names_only_need_to_be_unique_among_siblings() {
+        let system = ActorSystem::new(Default::default());
+        let eu = system.spawn_named("eu", || Orders).unwrap();
+        let us = system.spawn_named("us", || Orders).unwrap();
+        let eu_workers = eu.ask(Hire(vec!["worker-1"])).await;
+        let us_workers = us.ask(Hire(vec!["worker-1"])).await;
 
+        assert_eq!(eu_workers[0].path(), "/system/eu/worker-1");
+        assert_eq!(us_workers[0].path(), "/system/us/worker-1");
         assert_eq!(
-            total_deposits, expected_deposits,
-            "Total deposits don't match expected value"
+            system.lookup::<Worker>("eu/worker-1"),
+            Some(eu_workers[0].clone())
         );
         assert_eq!(
-            total_withdrawals, expected_withdrawals,
-            "Total withdrawals don't match expected value"
+            system.lookup::<Worker>("/system/us/worker-1"),
+            Some(us_workers[0].clone())
         );
+        system.shutdown().await;
+    }
 
-        // This assertion might fail due to race condition
-        assert_eq!(
-            final_balance, expected_balance,
-            "Final balance doesn't match expected value"
-        );
+    #[tokio::test]
+    async fn selections_resolve_wildcards_to_recipients() {
+        let system = ActorSystem::new(Default::default());
+        let orders = system.spawn_named("orders", || Orders).unwrap();
+        let workers = orders.ask(Hire(vec!["worker-1", "worker-2"])).await;
+        let auditor = orders.ask(HireAuditor).await;
+        let loner = system
+            .spawn_named("worker-3", || Worker { pings: 0 })
+            .unwrap();
 
-        // This assertion checks if the balance is consistent with deposits and withdrawals
+        let selected = system.select("/system/orders/*").addrs::<Worker>();
+        assert_eq!(selected, workers);
         assert_eq!(
-            final_balance,
-            initial_balance + total_deposits - total_withdrawals,
-            "Balance is inconsistent with recorded deposits and withdrawals"
+            system.select("/system/orders/worker-2").addrs::<Worker>(),
+            workers[1..2]
         );
+        assert_eq!(system.select("/system/*").addrs::<Worker>(), [loner]);
+        assert_eq!(system.select("/system/orders/*").addrs::<Orders>(), []);
+        assert_eq!(system.select("/system/**").addrs::<Worker>().len(), 4);
+        assert_eq!(orders.ask(CountWorkers).await, 3);
+
+        // Recipients cover every type that exposes the message.
+        let recipients = system.select("/system/orders/*").recipients::<Ping>();
+        let ids: Vec<_> = recipients.iter().map(|recipient| recipient.id()).collect();
+        let mut expected: Vec<_> = workers.iter().map(|worker| worker.id()).collect();
+        expected.push(auditor.id());
+        assert_eq!(ids, expected);
+        for recipient in recipients {
+            assert_eq!(recipient.ask(Ping).await, 1);
+        }
+        system.shutdown().await;
+        assert!(system.select("/system/**").addrs::<Worker>().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod snapshot_tests {
+    use crate::{Actor, ActorState, ActorSystem, Ctx, Handler, Message, Sender};
+    use std::time::Duration;
+
+    struct Worker;
+
+    impl Actor for Worker {}
+
+    #[derive(Message)]
+    struct Wait;
+
+    #[derive(Message)]
+    struct Crash;
+
+    impl Handler<Wait> for Worker {
+        async fn handle(&mut self, _: Wait, _: &Ctx<Self>) {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    impl Handler<Crash> for Worker {
+        async fn handle(&mut self, _: Crash, _: &Ctx<Self>) {
+            panic!("crash");
+        }
+    }
+
+    /// Never finishes starting.
+    struct Booting;
+
+    impl Actor for Booting {
+        async fn started(&mut self, _: &Ctx<Self>) {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn snapshot_describes_the_live_tree() {
+        let system = ActorSystem::builder()
+            .stop_timeout(Duration::from_millis(50))
+            .build();
+        let worker = system.spawn_named("worker", || Worker).unwrap();
+        worker.tell(Crash);
+        worker.tell(Wait);
+        worker.tell(Wait);
+        worker.tell(Wait);
+        let booting = system.spawn(|| Booting);
+        tokio::task::yield_now().await;
+
+        let tree = system.snapshot_tree();
+        assert_eq!(tree.path, "/system");
+        assert_eq!(tree.parent, None);
+        assert_eq!(tree.state, ActorState::Running);
+        assert_eq!(tree.iter().count(), 3);
+
+        let worker_node = tree.iter().find(|node| node.id == worker.id()).unwrap();
+        assert_eq!(worker_node.name.as_deref(), Some("worker"));
+        assert_eq!(worker_node.parent, Some(tree.id));
+        assert_eq!(worker_node.restarts, 1);
+        assert_eq!(worker_node.state, ActorState::Running);
+        assert_eq!(worker_node.mailbox_len, 2);
+
+        let booting_node = tree.iter().find(|node| node.id == booting.id()).unwrap();
+        assert_eq!(booting_node.name, None);
+        assert_eq!(booting_node.state, ActorState::Starting);
+
+        system.shutdown().await;
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn snapshot_serializes() {
+        let system = ActorSystem::new(Default::default());
+        system.spawn_named("worker", || Booting).unwrap();
+        tokio::task::yield_now().await;
+
+        let json = serde_json::to_value(system.snapshot_tree()).unwrap();
+        assert_eq!(json["path"], "/system");
+        assert_eq!(json["children"][0]["name"], "worker");
+        assert_eq!(json["children"][0]["state"], "starting");
+        assert_eq!(json["children"][0]["restarts"], 0);
+        assert_eq!(json["children"][0]["mailbox_len"], 0);
     }
 }